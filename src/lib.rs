@@ -1,2 +1,9 @@
 #[cfg(target_arch = "wasm32")]
+mod library;
+pub mod pattern;
+#[cfg(target_arch = "wasm32")]
+mod shader;
+pub mod sim;
+pub mod transform;
+#[cfg(target_arch = "wasm32")]
 mod window;
@@ -0,0 +1,96 @@
+use crate::pattern::Pattern;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use wasm_bindgen::JsCast;
+
+/// One pattern offered by the library, its RLE file is only fetched once it is picked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub url: String,
+}
+
+/// Parses a manifest like `[{"name": "Glider", "url": "patterns/glider.rle"}]`, skipping entries
+/// without a name or url.
+pub fn parse_manifest(text: &str) -> Result<Vec<Entry>, String> {
+    let manifest: serde_json::Value =
+        serde_json::from_str(text).map_err(|e| format!("Invalid manifest: {}", e))?;
+    let entries = manifest
+        .as_array()
+        .ok_or("The manifest must be an array of patterns")?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            Some(Entry {
+                name: entry.get("name")?.as_str()?.to_owned(),
+                url: entry.get("url")?.as_str()?.to_owned(),
+            })
+        })
+        .collect())
+}
+
+/// The entry called `name` in lowercase with dashes, `gosper-glider-gun` finds "Gosper glider
+/// gun", or whose file is called `name` without its extension.
+pub fn find<'a>(entries: &'a [Entry], name: &str) -> Option<&'a Entry> {
+    let slug = |text: &str| {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("-")
+    };
+    let name = slug(name);
+    entries.iter().find(|entry| {
+        let file = entry.url.rsplit('/').next().unwrap_or(&entry.url);
+        let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+        slug(&entry.name) == name || slug(stem) == name
+    })
+}
+
+/// Fetches the manifest at `manifest_url` and the pattern in it called `name`, see `find`.
+pub async fn load_named(manifest_url: &str, name: &str) -> Result<Pattern, String> {
+    let entries = parse_manifest(&fetch_text(manifest_url).await?)?;
+    let entry = find(&entries, name)
+        .ok_or_else(|| format!("No pattern called {:?} in {}", name, manifest_url))?;
+    let text = fetch_text(&entry.url).await?;
+    Pattern::parse(&entry.url, &text)
+}
+
+/// Fetches `url` as text, treating HTTP error statuses as failures.
+pub async fn fetch_text(url: &str) -> Result<String, String> {
+    let window = web_sys::window().unwrap();
+    let response = wasm_bindgen_futures::JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|e| format!("Could not fetch {}: {:?}", url, e))?
+        .unchecked_into::<web_sys::Response>();
+    if !response.ok() {
+        return Err(format!(
+            "Could not fetch {}: HTTP {}",
+            url,
+            response.status()
+        ));
+    }
+    let text = response
+        .text()
+        .map_err(|e| format!("Could not read {}: {:?}", url, e))?;
+    let text = wasm_bindgen_futures::JsFuture::from(text)
+        .await
+        .map_err(|e| format!("Could not read {}: {:?}", url, e))?;
+    Ok(text.as_string().unwrap_or_default())
+}
+
+/// Patterns of the manifest that were already fetched, keyed by url.
+pub type Cache = HashMap<String, Pattern>;
+
+/// Returns the pattern of `entry`, fetching and parsing it only the first time.
+pub async fn load(cache: &RefCell<Cache>, entry: &Entry) -> Result<Pattern, String> {
+    if let Some(pattern) = cache.borrow().get(&entry.url) {
+        return Ok(pattern.clone());
+    }
+    let text = fetch_text(&entry.url).await?;
+    let pattern = Pattern::parse(&entry.url, &text)?;
+    cache
+        .borrow_mut()
+        .insert(entry.url.clone(), pattern.clone());
+    Ok(pattern)
+}
@@ -0,0 +1,418 @@
+/// The most cells a parsed pattern may span, so a corrupt file can not run out of memory.
+const MAX_CELLS: u64 = 1 << 26;
+
+/// The file formats patterns can be exported in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Rle,
+    Life106,
+    Plaintext,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Rle => "rle",
+            Self::Life106 => "lif",
+            Self::Plaintext => "cells",
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "rle" => Ok(Self::Rle),
+            "life106" | "1.06" | "lif" => Ok(Self::Life106),
+            "plaintext" | "cells" => Ok(Self::Plaintext),
+            s => Err(format!(
+                "Unknown pattern format {:?}, expected rle, life106 or cells",
+                s
+            )),
+        }
+    }
+}
+
+/// A rectangle of cells as loaded from a pattern file, stored row by row.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<bool>,
+}
+
+/// Only the size and the live cell count, a board-sized pattern would flood the log otherwise.
+impl std::fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pattern")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field(
+                "population",
+                &self.cells.iter().filter(|&&cell| cell).count(),
+            )
+            .finish()
+    }
+}
+
+impl Pattern {
+    fn from_rows(rows: Vec<Vec<bool>>) -> Self {
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let height = rows.len();
+        let cells = rows
+            .into_iter()
+            .flat_map(|mut row| {
+                row.resize(width, false);
+                row
+            })
+            .collect();
+        Self {
+            width: width as u32,
+            height: height as u32,
+            cells,
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        self.cells[(y * self.width + x) as usize]
+    }
+
+    /// Parses a pattern file, picking the format from the file extension and falling back to
+    /// guessing from the contents.
+    pub fn parse(filename: &str, text: &str) -> Result<Self, String> {
+        let extension = filename
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("rle") => Self::parse_rle(text),
+            Some("cells") => Self::parse_plaintext(text),
+            _ if text.trim_start().starts_with("#Life 1.06") => Self::parse_life106(text),
+            _ if text.lines().any(|line| line.trim_start().starts_with("x ")) => {
+                Self::parse_rle(text)
+            }
+            _ => Self::parse_plaintext(text),
+        }
+    }
+
+    /// Parses the run length encoded format, e.g. `x = 3, y = 3\nbo$2bo$3o!` for a glider.
+    pub fn parse_rle(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let header = lines.next().ok_or("RLE file is empty")?;
+        if !header.starts_with('x') {
+            return Err(format!("Expected an RLE header, found {:?}", header));
+        }
+
+        let mut rows = vec![Vec::new()];
+        let mut width = 0;
+        let mut run = String::new();
+        'body: for line in lines {
+            for c in line.chars() {
+                let count = match run.parse::<u64>() {
+                    Ok(count) => count,
+                    Err(_) if run.is_empty() => 1,
+                    Err(_) => return Err(format!("A run of {} cells is too long", run)),
+                };
+                match c {
+                    '0'..='9' => {
+                        run.push(c);
+                        continue;
+                    }
+                    'b' | '.' | 'o' | 'A'..='X' => {
+                        let height = rows.len() as u64;
+                        let row = rows.last_mut().unwrap();
+                        width = width.max((row.len() as u64).saturating_add(count));
+                        if width.saturating_mul(height) > MAX_CELLS {
+                            return Err(format!("A {}x{} pattern is too large", width, height));
+                        }
+                        let alive = !matches!(c, 'b' | '.');
+                        row.extend((0..count).map(|_| alive));
+                    }
+                    '$' => {
+                        let height = (rows.len() as u64).saturating_add(count);
+                        if width.max(1).saturating_mul(height) > MAX_CELLS {
+                            return Err(format!("A {}x{} pattern is too large", width, height));
+                        }
+                        for _ in 0..count {
+                            rows.push(Vec::new());
+                        }
+                    }
+                    '!' => break 'body,
+                    c if c.is_whitespace() => {}
+                    c => return Err(format!("Unexpected {:?} in RLE data", c)),
+                }
+                run.clear();
+            }
+        }
+
+        Ok(Self::from_rows(rows))
+    }
+
+    /// Parses the plaintext `.cells` format with `.` for dead and `O` for live cells.
+    pub fn parse_plaintext(text: &str) -> Result<Self, String> {
+        let rows = text
+            .lines()
+            .filter(|line| !line.starts_with('!'))
+            .map(|line| {
+                line.trim_end()
+                    .chars()
+                    .map(|c| match c {
+                        '.' => Ok(false),
+                        'O' | 'o' | '*' => Ok(true),
+                        c => Err(format!("Unexpected {:?} in plaintext pattern", c)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::from_rows(rows))
+    }
+
+    /// Parses the Life 1.06 format, a `#Life 1.06` header followed by one `x y` line per live
+    /// cell. The coordinates may be negative, the pattern starts at the smallest of them.
+    pub fn parse_life106(text: &str) -> Result<Self, String> {
+        let cells = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut coordinates = line.split_whitespace().map(str::parse::<i64>);
+                match (coordinates.next(), coordinates.next(), coordinates.next()) {
+                    (Some(Ok(x)), Some(Ok(y)), None) => Ok((x, y)),
+                    _ => Err(format!("Expected a cell like \"-1 2\", found {:?}", line)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some(x0) = cells.iter().map(|(x, _)| *x).min() else {
+            return Ok(Self::from_rows(Vec::new()));
+        };
+        let y0 = cells.iter().map(|(_, y)| *y).min().unwrap();
+        let width = cells
+            .iter()
+            .map(|(x, _)| x.abs_diff(x0).saturating_add(1))
+            .max()
+            .unwrap();
+        let height = cells
+            .iter()
+            .map(|(_, y)| y.abs_diff(y0).saturating_add(1))
+            .max()
+            .unwrap();
+        if width.saturating_mul(height) > MAX_CELLS {
+            return Err(format!("A {}x{} pattern is too large", width, height));
+        }
+        let mut rows = vec![vec![false; width as usize]; height as usize];
+        for (x, y) in cells {
+            rows[(y - y0) as usize][(x - x0) as usize] = true;
+        }
+        Ok(Self::from_rows(rows))
+    }
+
+    /// The smallest rectangle containing every live cell, empty if there are none.
+    pub fn trimmed(&self) -> Self {
+        let Some((x0, y0, x1, y1)) = self.bounds() else {
+            return Self::from_rows(Vec::new());
+        };
+        Self::from_rows(
+            (y0..=y1)
+                .map(|y| (x0..=x1).map(|x| self.get(x, y)).collect())
+                .collect(),
+        )
+    }
+
+    /// The inclusive rectangle (x0, y0, x1, y1) around the live cells, `None` if there are none.
+    pub fn bounds(&self) -> Option<(u32, u32, u32, u32)> {
+        let live = || (0..self.height).flat_map(|y| (0..self.width).map(move |x| (x, y)));
+        let live = || live().filter(|&(x, y)| self.get(x, y));
+        let x0 = live().map(|(x, _)| x).min()?;
+        let x1 = live().map(|(x, _)| x).max().unwrap();
+        let y0 = live().map(|(_, y)| y).min().unwrap();
+        let y1 = live().map(|(_, y)| y).max().unwrap();
+        Some((x0, y0, x1, y1))
+    }
+
+    /// Surrounded by `margin` dead cells on every side.
+    pub fn padded(&self, margin: u32) -> Self {
+        let (width, height) = (self.width + 2 * margin, self.height + 2 * margin);
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (x, y) = (x.wrapping_sub(margin), y.wrapping_sub(margin));
+                x < self.width && y < self.height && self.get(x, y)
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Stretched or squeezed to `width` by `height`, every cell taking the nearest cell of the
+    /// original.
+    pub fn scaled(&self, width: u32, height: u32) -> Self {
+        if self.width == 0 || self.height == 0 {
+            return Self {
+                width,
+                height,
+                cells: vec![false; (width * height) as usize],
+            };
+        }
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let source_x = (x as u64 * self.width as u64 / width as u64) as u32;
+                let source_y = (y as u64 * self.height as u64 / height as u64) as u32;
+                self.get(source_x, source_y)
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn export(&self, format: Format) -> String {
+        match format {
+            Format::Rle => self.to_rle(),
+            Format::Life106 => self.to_life106(),
+            Format::Plaintext => self.to_plaintext(),
+        }
+    }
+
+    /// Writes the run length encoded format that `parse_rle` reads, with lines of at most 70
+    /// characters.
+    pub fn to_rle(&self) -> String {
+        let mut runs = Vec::new();
+        let mut push = |count: u32, tag: char| match count {
+            0 => {}
+            1 => runs.push(tag.to_string()),
+            n => runs.push(format!("{}{}", n, tag)),
+        };
+        // Row the runs are currently in
+        let mut current = 0;
+        for y in 0..self.height {
+            let row: Vec<bool> = (0..self.width).map(|x| self.get(x, y)).collect();
+            // Trailing dead cells of a row are implied
+            let Some(end) = row.iter().rposition(|&alive| alive) else {
+                continue;
+            };
+            push(y - current, '$');
+            current = y;
+            let mut x = 0;
+            while x <= end {
+                let alive = row[x];
+                let run = row[x..=end].iter().take_while(|&&a| a == alive).count();
+                push(run as u32, if alive { 'o' } else { 'b' });
+                x += run;
+            }
+        }
+        push(1, '!');
+
+        let mut rle = format!("x = {}, y = {}\n", self.width, self.height);
+        let mut line_length = 0;
+        for run in runs {
+            if line_length + run.len() > 70 {
+                rle.push('\n');
+                line_length = 0;
+            }
+            line_length += run.len();
+            rle.push_str(&run);
+        }
+        rle.push('\n');
+        rle
+    }
+
+    /// Writes the plaintext `.cells` format that `parse_plaintext` reads.
+    pub fn to_plaintext(&self) -> String {
+        let mut text = String::from("!Name: life\n");
+        for y in 0..self.height {
+            text.extend((0..self.width).map(|x| if self.get(x, y) { 'O' } else { '.' }));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Writes the Life 1.06 format with coordinates relative to the center of the pattern.
+    pub fn to_life106(&self) -> String {
+        let (cx, cy) = (self.width as i64 / 2, self.height as i64 / 2);
+        let mut text = String::from("#Life 1.06\n");
+        for y in 0..self.height {
+            for x in (0..self.width).filter(|&x| self.get(x, y)) {
+                text.push_str(&format!("{} {}\n", x as i64 - cx, y as i64 - cy));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed patterns, so every format reads them back at the same size.
+    const PATTERNS: &[&[&str]] = &[
+        &[".O.", "..O", "OOO"],
+        &["O..O", "....", ".OO."],
+        &["OOOOOOOOOOOO.O", "", "", ".............O"],
+        &["O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O.O"],
+    ];
+
+    /// A pattern with the cells marked `O` in `rows` alive.
+    fn pattern(rows: &[&str]) -> Pattern {
+        Pattern::from_rows(
+            rows.iter()
+                .map(|row| row.chars().map(|c| c == 'O').collect())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn rle_round_trips() {
+        for rows in PATTERNS {
+            let pattern = pattern(rows);
+            assert_eq!(Pattern::parse_rle(&pattern.to_rle()), Ok(pattern));
+        }
+    }
+
+    #[test]
+    fn plaintext_round_trips() {
+        for rows in PATTERNS {
+            let pattern = pattern(rows);
+            assert_eq!(
+                Pattern::parse_plaintext(&pattern.to_plaintext()),
+                Ok(pattern)
+            );
+        }
+    }
+
+    #[test]
+    fn life106_round_trips() {
+        for rows in PATTERNS {
+            let pattern = pattern(rows);
+            assert_eq!(Pattern::parse_life106(&pattern.to_life106()), Ok(pattern));
+        }
+    }
+
+    #[test]
+    fn long_rle_lines_are_wrapped() {
+        let rle = pattern(PATTERNS[3]).to_rle();
+        assert!(rle.lines().count() > 2);
+        assert!(rle.lines().all(|line| line.len() <= 70));
+    }
+
+    #[test]
+    fn oversized_patterns_are_rejected() {
+        assert!(Pattern::parse_rle("99999999999o!").is_err());
+        assert!(Pattern::parse_rle("100000o99999$o!").is_err());
+        assert!(Pattern::parse_life106("#Life 1.06\n0 0\n100000 100000").is_err());
+        assert!(Pattern::parse_life106(
+            "#Life 1.06\n-9223372036854775808 0\n9223372036854775807 0"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,130 @@
+/// The modules `shader.wgsl` can pull in with a `//!include name.wgsl` line.
+const MODULES: [(&str, &str); 5] = [
+    ("color.wgsl", include_str!("shaders/color.wgsl")),
+    ("neighbors.wgsl", include_str!("shaders/neighbors.wgsl")),
+    ("rules.wgsl", include_str!("shaders/rules.wgsl")),
+    ("draw.wgsl", include_str!("shaders/draw.wgsl")),
+    ("custom.wgsl", include_str!("shaders/custom.wgsl")),
+];
+
+/// Compiler messages about lines of a custom rule point here.
+const CUSTOM_RULE: &str = "custom rule";
+
+/// `shader.wgsl` with every include replaced by the module it names, remembering which module
+/// each line came from so compiler messages can point into the modules.
+pub struct Source {
+    pub text: String,
+    /// Module and line number within it of every line of `text`
+    lines: Vec<(&'static str, usize)>,
+}
+
+impl Source {
+    /// Resolves the includes of `shader.wgsl`. Every module is included once even if several
+    /// modules include it, unknown modules and include cycles are errors.
+    ///
+    /// `custom_rule` replaces the lines between `//!rule` and `//!end`, the body of
+    /// `custom_rule` in `custom.wgsl`.
+    pub fn assemble(custom_rule: Option<&str>) -> Result<Self, String> {
+        let mut source = Self {
+            text: String::new(),
+            lines: Vec::new(),
+        };
+        let mut stack = Vec::new();
+        let mut included = Vec::new();
+        source.append(
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            custom_rule,
+            &mut stack,
+            &mut included,
+        )?;
+        Ok(source)
+    }
+
+    fn append(
+        &mut self,
+        name: &'static str,
+        text: &'static str,
+        custom_rule: Option<&str>,
+        stack: &mut Vec<&'static str>,
+        included: &mut Vec<&'static str>,
+    ) -> Result<(), String> {
+        stack.push(name);
+        // Inside the default body of the custom rule while a replacement is given
+        let mut replaced = false;
+        for (i, line) in text.lines().enumerate() {
+            match (line.trim(), custom_rule) {
+                ("//!rule", Some(rule)) => {
+                    for (j, rule_line) in rule.lines().enumerate() {
+                        self.text.push_str(rule_line);
+                        self.text.push('\n');
+                        self.lines.push((CUSTOM_RULE, j + 1));
+                    }
+                    replaced = true;
+                    continue;
+                }
+                ("//!end", _) => {
+                    replaced = false;
+                    continue;
+                }
+                _ if replaced => continue,
+                _ => {}
+            }
+            let Some(include) = line.trim().strip_prefix("//!include ") else {
+                self.text.push_str(line);
+                self.text.push('\n');
+                self.lines.push((name, i + 1));
+                continue;
+            };
+            let include = include.trim();
+            let Some(&(module, module_text)) = MODULES.iter().find(|(m, _)| *m == include) else {
+                return Err(format!(
+                    "{}:{}: unknown shader module {:?}",
+                    name,
+                    i + 1,
+                    include
+                ));
+            };
+            if stack.contains(&module) {
+                return Err(format!(
+                    "{}:{}: {} includes itself through {}",
+                    name,
+                    i + 1,
+                    module,
+                    stack.join(" -> ")
+                ));
+            }
+            if !included.contains(&module) {
+                included.push(module);
+                self.append(module, module_text, custom_rule, stack, included)?;
+            }
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    /// Rewrites the `wgsl:line:column` locations in a compiler message into the module and line
+    /// they refer to, e.g. `wgsl:130:5` into `draw.wgsl:12:5`.
+    pub fn annotate(&self, message: &str) -> String {
+        let mut annotated = String::new();
+        let mut rest = message;
+        while let Some(start) = rest.find("wgsl:") {
+            annotated.push_str(&rest[..start]);
+            rest = &rest[start + "wgsl:".len()..];
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let location = rest[..digits]
+                .parse::<usize>()
+                .ok()
+                .and_then(|line| self.lines.get(line.checked_sub(1)?));
+            match location {
+                Some((module, line)) => annotated.push_str(&format!("{}:{}", module, line)),
+                None => annotated.push_str(&format!("wgsl:{}", &rest[..digits])),
+            }
+            rest = &rest[digits..];
+        }
+        annotated.push_str(rest);
+        annotated
+    }
+}
@@ -1,5 +1,21 @@
 @group(0) @binding(0) var texture: texture_2d<f32>;
 @group(1) @binding(0) var<uniform> uniforms: Uniforms;
+// Only bound in the compute pipeline
+@group(2) @binding(0) var<uniform> rule: Rule;
+// Only bound in the render pipeline, holds the second layer when two are simulated
+@group(0) @binding(1) var second_texture: texture_2d<f32>;
+// Bound in the render pipeline and while generating mips: the filterable display copy of the
+// first layer and the trilinear sampler for it
+@group(0) @binding(2) var display_texture: texture_2d<f32>;
+@group(0) @binding(3) var display_sampler: sampler;
+// Colors by cell age, see `palette_len`
+@group(0) @binding(4) var palette: texture_2d<f32>;
+// The first layer one generation back, crossfaded from in slow motion
+@group(0) @binding(5) var previous_texture: texture_2d<f32>;
+// The live cells blurred at a quarter of the board size, added on top while the glow is on
+@group(0) @binding(6) var glow_texture: texture_2d<f32>;
+// 0.5 for cells of still lifes and 1 for cells of oscillators found by classifying the board
+@group(0) @binding(7) var classes: texture_2d<f32>;
 
 //https://stackoverflow.com/questions/5149544/can-i-generate-a-random-number-inside-a-pixel-shader
 fn random(p: vec2<f32>) -> f32 {
@@ -11,14 +27,235 @@ fn random(p: vec2<f32>) -> f32 {
 }
 
 struct Uniforms {
+    // Cell under the cursor while drawing
     mouse_pos: vec2<f32>,
+    // Cell the current stroke started on, picks the stroke's color, far off the board like
+    // `mouse_pos` while not drawing
     seed: vec2<f32>,
+    // Inclusive cell rectangle (x0, y0, x1, y1), negative when nothing is selected
+    selection: vec4<f32>,
+    // Inclusive cell rectangle (x0, y0, x1, y1) that is simulated, everything outside stays dead
+    active_region: vec4<f32>,
+    // Number of simulated layers, 1 or 2
+    layers: u32,
+    // Cell under the cursor, far off the board while the cursor is outside the canvas
+    hover_pos: vec2<f32>,
+    // Cursor position before the latest move, gives the drag direction for the line brush
+    prev_mouse_pos: vec2<f32>,
+    brush_radius: f32,
+    // 0 = circle, 1 = square, 2 = line along the drag direction
+    brush_shape: u32,
+    // 0 = classic discrete Life, 1 = SmoothLife, 2 = the custom rule
+    sim_kind: u32,
+    // SmoothLife: radius of the cell's own disk and of the surrounding annulus
+    inner_radius: f32,
+    outer_radius: f32,
+    // SmoothLife: width of the birth/death transitions
+    sigmoid_width: f32,
+    // View: the cell at the top-left corner of the canvas and canvas pixels per cell
+    pan: vec2<f32>,
+    zoom: f32,
+    // 1 if the minimap should be shown while zoomed in
+    minimap: u32,
+    // 0 = filled squares, 1 = anti-aliased circles
+    cell_style: u32,
+    // Chebyshev radius of the neighborhood, above 1 the ranges below replace the rule bitmasks
+    neighbor_radius: u32,
+    // Inclusive ranges of live neighbor counts for birth and survival
+    min_birth: u32,
+    max_birth: u32,
+    min_survive: u32,
+    max_survive: u32,
+    // Cells between major gridlines, 0 hides the grid
+    major_grid_interval: u32,
+    // Blended over the cells by their alpha, minor lines only show once cells are big enough
+    minor_grid_color: vec4<f32>,
+    major_grid_color: vec4<f32>,
+    // Cell of the keyboard edit cursor, far off the board while it is hidden
+    edit_cursor: vec2<f32>,
+    // 1 if the board is drawn from the mipmapped display copy while zoomed out
+    filtered_zoom: u32,
+    // Debugging: 0 shows the cells normally, 1 to 4 show the R, G, B or A channel as grayscale
+    view_channel: u32,
+    // Cells this close to the edges of the board are killed every generation so that nothing
+    // wraps around, 0 keeps the edges open
+    kill_margin: u32,
+    // Colors in the palette: the first for dead cells, then one per generation of age. 0 shows
+    // cells in their own color
+    palette_len: u32,
+    // Size of the canvas in pixels
+    canvas_size: vec2<f32>,
+    // What neighbors past the edges of the board are: 0 wraps around, 1 dead, 2 mirrored
+    boundary_mode: u32,
+    // Fraction of the cells under the brush that are set each frame, 1 for a solid brush
+    brush_density: f32,
+    // Counts frames while spraying, so every frame sets different cells
+    frame: u32,
+    // Seconds since the page loaded as of the current frame, advances without any input
+    time: f32,
+    // Corner the generation counter is drawn in: 0 hidden, 1 top-left, 2 top-right,
+    // 3 bottom-left, 4 bottom-right
+    counter_corner: u32,
+    // Current generation, wrapping around after 2^32
+    generation: u32,
+    // Whether the brush continues on the opposite edge: 0 while the board wraps around, 1 always,
+    // 2 never
+    brush_wrap: u32,
+    // Size of the board in cells, for passes that do not bind it
+    board_size: vec2<f32>,
+    // How far the display has crossfaded from the previous to the current generation, 1 shows
+    // only the current one
+    blend: f32,
+    // Time newborn cells grow to their full size over, 0 draws every cell at full size
+    birth_animation_ms: f32,
+    // How long each generation is on screen, and how long the current one has been
+    ms_per_generation: f32,
+    since_step_ms: f32,
+    // 1 if the brush positions are the centers of their cells rather than the corners
+    snap: u32,
+    // Tints blended over cells that die or are born in the next generation, by their alpha
+    dying_color: vec4<f32>,
+    born_color: vec4<f32>,
+    // 1 if the next generation is previewed with the tints above
+    predict: u32,
+    // 1 if cells that changed since the previous step are drawn bright and the rest dim
+    show_changes: u32,
+    // First cell of the straight line being drawn, far off the board while there is none
+    anchor: vec2<f32>,
+    // 1 if the board is drawn from the display copy with linear filtering while zoomed in
+    smooth_zoom: u32,
+    // Which cells the classic and custom rules count as neighbors: 0 the eight around (Moore),
+    // 1 only the four orthogonal ones (von Neumann)
+    neighborhood: u32,
+    // Size of the copied region outlined at the cursor where it would be pasted, 0 while hidden
+    paste_size: vec2<f32>,
+    // Corner the top-left cell of the copied region was turned or mirrored into, clockwise from
+    // 0 at the top-left
+    paste_corner: u32,
+    // 0 advances every cell every generation, 1 only those with `x + y` as even or odd as `phase`
+    update_scheme: u32,
+    // Parity of the generation being computed, set for every step of the checkerboard scheme
+    phase: u32,
+    // 1 if live cells glow into their surroundings, `glow_radius` texels of `glow_texture` far
+    // and `glow_intensity` times as bright as the cells
+    glow: u32,
+    glow_radius: f32,
+    glow_intensity: f32,
+    // Multiplies `brush_radius` while the brush tapers with the speed of the stroke, 1 otherwise
+    brush_scale: f32,
+    // 1 if the cells in `classes` are tinted
+    classified: u32,
+    // Cells the content is shown shifted by around the torus, the cell shown at view position p
+    // is (p + content_offset) modulo the board size
+    content_offset: vec2<f32>,
+    // 1 while moving the view past an edge shifts the content instead
+    scrolling: u32,
+    // 1 if odd rows are shown shifted right by half a cell like bricks, which only changes where
+    // cells show up, every cell keeps the same eight neighbors
+    brick: u32,
 };
 
+//!include color.wgsl
+//!include neighbors.wgsl
+//!include rules.wgsl
+//!include draw.wgsl
+//!include custom.wgsl
+
+const MINIMAP_SIZE: f32 = 192.0;
+const MINIMAP_MARGIN: f32 = 8.0;
+
+// Minimap in the bottom-right corner: the whole board point sampled at low resolution with the
+// visible part outlined. Returns a negative alpha for pixels outside the minimap.
+fn minimap(screen: vec2<f32>) -> vec4<f32> {
+    let canvas = uniforms.canvas_size;
+    let origin = canvas - MINIMAP_MARGIN - MINIMAP_SIZE;
+    let local = screen - origin;
+    if uniforms.minimap == 0u || uniforms.zoom <= 1.0 || any(local < vec2(0.0)) || any(local >= vec2(MINIMAP_SIZE)) {
+        return vec4(0.0, 0.0, 0.0, -1.0);
+    }
+
+    let size = vec2<f32>(textureDimensions(texture));
+    // The same shift as the board, so the view rectangle marks what is shown
+    let cell = (local / MINIMAP_SIZE * size + uniforms.content_offset) % size;
+    let view_min = uniforms.pan / size * MINIMAP_SIZE;
+    let view_max = (uniforms.pan + canvas / uniforms.zoom) / size * MINIMAP_SIZE;
+    let on_view_edge = all(local >= view_min - 1.0) && all(local <= view_max + 1.0)
+        && (any(local < view_min + 1.0) || any(local > view_max - 1.0));
+    if on_view_edge {
+        return vec4(1.0, 1.0, 0.0, 1.0);
+    }
+    if any(local < vec2(1.0)) || any(local >= vec2(MINIMAP_SIZE - 1.0)) {
+        return vec4(0.5, 0.5, 0.5, 1.0);
+    }
+    return vec4(textureLoad(texture, vec2<i32>(cell), 0).rgb, 1.0);
+}
+
+// Canvas pixels per pixel of the 3x5 counter font
+const COUNTER_SCALE: f32 = 3.0;
+const COUNTER_MARGIN: f32 = 8.0;
+
+// The generation in digits of a 3x5 bitmap font on a black box in the chosen corner, so it ends up
+// in screenshots and recordings of the canvas. Returns a negative alpha for pixels outside the box.
+fn generation_counter(screen: vec2<f32>) -> vec4<f32> {
+    if uniforms.counter_corner == 0u {
+        return vec4(0.0, 0.0, 0.0, -1.0);
+    }
+    // Each glyph is 15 bits, row by row from the top with the leftmost pixel highest
+    var glyphs = array<u32, 10>(
+        0x7B6Fu, 0x2C97u, 0x73E7u, 0x73CFu, 0x5BC9u, 0x79CFu, 0x79EFu, 0x7249u, 0x7BEFu, 0x7BCFu
+    );
+
+    var digits = 1u;
+    for (var rest = uniforms.generation / 10u; rest > 0u; rest /= 10u) {
+        digits += 1u;
+    }
+    // One font pixel of padding around glyphs that advance by four font pixels
+    let box_size = vec2(f32(4u * digits + 1u), 7.0) * COUNTER_SCALE;
+    var origin = vec2(COUNTER_MARGIN);
+    if uniforms.counter_corner == 2u || uniforms.counter_corner == 4u {
+        origin.x = uniforms.canvas_size.x - COUNTER_MARGIN - box_size.x;
+    }
+    if uniforms.counter_corner >= 3u {
+        origin.y = uniforms.canvas_size.y - COUNTER_MARGIN - box_size.y;
+    }
+    let local = screen - origin;
+    if any(local < vec2(0.0)) || any(local >= box_size) {
+        return vec4(0.0, 0.0, 0.0, -1.0);
+    }
+
+    let font = vec2<i32>(floor(local / COUNTER_SCALE)) - 1;
+    let slot = font.x / 4;
+    let column = font.x % 4;
+    if font.x < 0 || font.y < 0 || font.y >= 5 || column == 3 || u32(slot) >= digits {
+        return vec4(0.0, 0.0, 0.0, 1.0);
+    }
+    var digit = uniforms.generation;
+    for (var i = u32(slot) + 1u; i < digits; i++) {
+        digit /= 10u;
+    }
+    let bit = 14 - (font.y * 3 + column);
+    if ((glyphs[digit % 10u] >> u32(bit)) & 1u) == 1u {
+        return vec4(1.0, 1.0, 1.0, 1.0);
+    }
+    return vec4(0.0, 0.0, 0.0, 1.0);
+}
+
+fn in_active_region(x: i32, y: i32) -> bool {
+    let region = vec4<i32>(uniforms.active_region);
+    return x >= region.x && x <= region.z && y >= region.y && y <= region.w;
+}
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
 };
 
+// Every pass draws `0..3`, one triangle with the corners (5, -2), (0, 2) and (-5, -2) in
+// normalized device coordinates. It is counter-clockwise, so it survives back-face culling, and
+// covers the whole [-1, 1]^2 viewport with the rest clipped away. No UVs are passed: fragments
+// address the board through `clip_position.xy`, which is in framebuffer pixels with the origin at
+// the top-left and y pointing down, so the fragment of texel (x, y) sits at (x + 0.5, y + 0.5)
+// and textures are read without flips or offsets. `fullscreen_triangle_covers_the_board` in the
+// tests of `window.rs` checks this through `fs_coordinates`
 @vertex
 fn vs_compute(
     @builtin(vertex_index) in_vertex_index: u32,
@@ -30,52 +267,162 @@ fn vs_compute(
     return out;
 }
 
+// Cells stop aging here, way past the longest palette and still exact in 16-bit floats
+const MAX_AGE: u32 = 1024u;
+
 @fragment
 fn fs_compute(in: VertexOutput) -> @location(0) vec4<f32> {
     let x = i32(in.clip_position.x);
     let y = i32(in.clip_position.y);
 
-    if x == 50 || y == 50 {
-        return vec4(1.0, 0.0, 0.0, 1.0);
+    if !in_active_region(x, y) {
+        return vec4(0.0, 0.0, 0.0, 1.0);
     }
-    let diff = in.clip_position.xy - uniforms.mouse_pos;
-    let dist = dot(diff, diff);
 
-    if dist < 122.0 {
-        let r = vec3(random(uniforms.seed), random(2.0 * uniforms.seed), random(3.0 * uniforms.seed));
-        let rlength = length(r);
+    let size = vec2<i32>(textureDimensions(texture));
+    let margin = i32(uniforms.kill_margin);
+    if x < margin || y < margin || x >= size.x - margin || y >= size.y - margin {
+        return vec4(0.0, 0.0, 0.0, 1.0);
+    }
 
-        return vec4(r / rlength, 1.0);
+    if uniforms.update_scheme == 1u && u32(x + y) % 2u != uniforms.phase {
+        return load_cell(vec2(x, y));
     }
 
-    var sum = vec3(0.0, 0.0, 0.0);
-    sum += textureLoad(texture, vec2(x - 1, y), 0).rgb;
-    sum += textureLoad(texture, vec2(x + 1, y), 0).rgb;
-    sum += textureLoad(texture, vec2(x, y + 1), 0).rgb;
-    sum += textureLoad(texture, vec2(x, y - 1), 0).rgb;
-    sum += textureLoad(texture, vec2(x - 1, y - 1), 0).rgb;
-    sum += textureLoad(texture, vec2(x - 1, y + 1), 0).rgb;
-    sum += textureLoad(texture, vec2(x + 1, y - 1), 0).rgb;
-    sum += textureLoad(texture, vec2(x + 1, y + 1), 0).rgb;
+    if x == 50 || y == 50 {
+        return vec4(1.0, 0.0, 0.0, 1.0);
+    }
+    if uniforms.sim_kind == 1u {
+        return smooth_life(x, y);
+    }
+    if uniforms.neighbor_radius > 1u && uniforms.sim_kind == 0u {
+        return larger_than_life(x, y);
+    }
+
+    let sum = neighbor_sum(x, y);
     let sumlength = length(sum);
 
-    let current = textureLoad(texture, vec2<i32>(x, y), 0).rgb;
+    let count = neighbor_count(sumlength);
+    if uniforms.sim_kind == 2u {
+        return custom_rule(x, y, load_cell(vec2(x, y)), sum, count, uniforms.generation);
+    }
+
+    let current = load_cell(vec2(x, y)).rgb;
     if dot(current, current) <= 0.5 {
-        if sumlength >= 3.0 - 0.1 && sumlength <= 3.0 + 0.1 {
+        if rule_allows(rule.birth, count) {
             let color = sum/sumlength;
             return vec4(color, 1.0);
         } else {
             return vec4(0.0, 0.0, 0.0, 1.0);
         }
     } else {
-        if sumlength >= 2.0 - 0.1 && sumlength <= 3.0 + 0.1 {
-            return vec4(current, 1.0);
+        if rule_allows(rule.survive, count) {
+            // Alpha counts the generations a cell has been alive, used to pick its palette color
+            let age = load_cell(vec2(x, y)).a;
+            return vec4(current, min(age + 1.0, f32(MAX_AGE)));
         } else {
             return vec4(0.0, 0.0, 0.0, 1.0);
         }
     }
 }
 
+// Runs once after switching `sim_kind`, moving every cell into the channels the new mode uses:
+// - Classic and custom: rgb is black or the unit length color of a live cell, alpha its age in
+//   generations
+// - SmoothLife: the length of rgb is the state in [0, 1], alpha is unused and kept at 1
+// Leftover fractional states would otherwise break the neighbor counts of the classic rules, and
+// leftover ages would color SmoothLife cells by the palette.
+@fragment
+fn fs_normalize(in: VertexOutput) -> @location(0) vec4<f32> {
+    let rgb = load_cell(vec2<i32>(in.clip_position.xy)).rgb;
+    if uniforms.sim_kind == 1u {
+        let state = min(length(rgb), 1.0);
+        if state > 0.0 {
+            return vec4(rgb / length(rgb) * state, 1.0);
+        }
+    } else if dot(rgb, rgb) > 0.5 {
+        return vec4(normalize(rgb), 1.0);
+    }
+    return vec4(0.0, 0.0, 0.0, 1.0);
+}
+
+// Whether the cell at (`x`, `y`) is alive in the next generation of the classic rules, the same
+// decision `fs_compute` makes, for previewing it
+fn alive_next(x: i32, y: i32) -> bool {
+    let size = vec2<i32>(textureDimensions(texture));
+    let margin = i32(uniforms.kill_margin);
+    if !in_active_region(x, y) || x < margin || y < margin || x >= size.x - margin || y >= size.y - margin {
+        return false;
+    }
+    // `phase` is still the one of the latest step, the next one updates the other half
+    if uniforms.update_scheme == 1u && u32(x + y) % 2u == uniforms.phase {
+        let current = load_cell(vec2(x, y)).rgb;
+        return dot(current, current) > 0.5;
+    }
+    if uniforms.neighbor_radius > 1u {
+        let next = larger_than_life(x, y).rgb;
+        return dot(next, next) > 0.5;
+    }
+
+    let count = neighbor_count(length(neighbor_sum(x, y)));
+    let current = load_cell(vec2(x, y)).rgb;
+    if dot(current, current) <= 0.5 {
+        return rule_allows(rule.birth, count);
+    }
+    return rule_allows(rule.survive, count);
+}
+
+// First level of the display copy, the cells as they are
+@fragment
+fn fs_display_base(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4(textureLoad(texture, vec2<i32>(in.clip_position.xy), 0).rgb, 1.0);
+}
+
+// Every further level: each texel covers 2x2 texels of the previous level, sampling right between
+// them averages all four
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(display_texture));
+    return textureSampleLevel(display_texture, display_sampler, in.clip_position.xy * 2.0 / size, 0.0);
+}
+
+// One pass of the glow blur along `direction`, a gaussian over `glow_radius` texels on each side
+// of the texel of `display_texture`, which has the size of the target
+fn glow_blur(pos: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(display_texture));
+    let radius = i32(uniforms.glow_radius);
+    let sigma = max(uniforms.glow_radius / 2.0, 0.5);
+    var sum = vec3(0.0);
+    var total = 0.0;
+    for (var i = -radius; i <= radius; i++) {
+        let weight = exp(-f32(i * i) / (2.0 * sigma * sigma));
+        let at = (pos + direction * f32(i)) / size;
+        sum += weight * textureSampleLevel(display_texture, display_sampler, at, 0.0).rgb;
+        total += weight;
+    }
+    return vec4(sum / total, 1.0);
+}
+
+@fragment
+fn fs_glow_horizontal(in: VertexOutput) -> @location(0) vec4<f32> {
+    return glow_blur(in.clip_position.xy, vec2(1.0, 0.0));
+}
+
+@fragment
+fn fs_glow_vertical(in: VertexOutput) -> @location(0) vec4<f32> {
+    return glow_blur(in.clip_position.xy, vec2(0.0, 1.0));
+}
+
+// `color` of the board at `uv` with the glow added while it is on
+fn glowing(color: vec4<f32>, uv: vec2<f32>) -> vec4<f32> {
+    if uniforms.glow == 0u {
+        return color;
+    }
+    let glow = textureSampleLevel(glow_texture, display_sampler, uv, 0.0).rgb;
+    return vec4(color.rgb + glow * uniforms.glow_intensity, color.a);
+}
+
+// The same fullscreen triangle as `vs_compute`, over the canvas instead of the board
 @vertex
 fn vs_main(
     @builtin(vertex_index) in_vertex_index: u32,
@@ -87,10 +434,216 @@ fn vs_main(
     return out;
 }
 
+// Whether `cell` is on the straight line from `start` to `end`, the same cells as `line_cells` on
+// the CPU: one per step along the longer axis, the other coordinate rounded half up
+fn on_line(cell: vec2<i32>, start: vec2<i32>, end: vec2<i32>) -> bool {
+    let d = end - start;
+    let steps = max(abs(d.x), abs(d.y));
+    if steps == 0 {
+        return all(cell == start);
+    }
+    // Steps from `start` along the longer axis, negative before it
+    let dir = select(vec2(1), vec2(-1), d < vec2(0));
+    var i = (cell.x - start.x) * dir.x;
+    if abs(d.y) > abs(d.x) {
+        i = (cell.y - start.y) * dir.y;
+    }
+    if i < 0 || i > steps {
+        return false;
+    }
+    let n = f32(steps);
+    let expected = start + vec2<i32>(floor((2.0 * vec2<f32>(i * d) + n) / (2.0 * n)));
+    return all(cell == expected);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let x = i32(in.clip_position.x);
-    let y = i32(in.clip_position.y);
+    let counter = generation_counter(in.clip_position.xy);
+    if counter.a >= 0.0 {
+        return counter;
+    }
+
+    let mini = minimap(in.clip_position.xy);
+    if mini.a >= 0.0 {
+        return mini;
+    }
+
+    // Zoomed out the board does not cover the whole canvas
+    let view_pos = uniforms.pan + in.clip_position.xy / uniforms.zoom;
+    let board = vec2<f32>(textureDimensions(texture));
+    if any(view_pos < vec2(0.0)) || any(view_pos >= board) {
+        return vec4(0.0, 0.0, 0.0, 1.0);
+    }
+
+    var cell_pos = (view_pos + uniforms.content_offset) % board;
+    if uniforms.brick == 1u && i32(cell_pos.y) % 2 == 1 {
+        cell_pos.x = (cell_pos.x - 0.5 + board.x) % board.x;
+    }
+    let cell = floor(cell_pos);
+    let x = i32(cell.x);
+    let y = i32(cell.y);
+
+    let sel = vec4<i32>(uniforms.selection);
+    if uniforms.selection.x >= 0.0 && x >= sel.x && x <= sel.z && y >= sel.y && y <= sel.w {
+        if x == sel.x || x == sel.z || y == sel.y || y == sel.w {
+            // Dashed marquee
+            if ((x + y) / 4) % 2 == 0 {
+                return vec4(1.0, 1.0, 1.0, 1.0);
+            }
+            return vec4(0.0, 0.0, 0.0, 1.0);
+        }
+    }
+
+    if !in_active_region(x, y) {
+        // Faint border just outside the simulated region
+        let region = vec4<i32>(uniforms.active_region);
+        if x >= region.x - 1 && x <= region.z + 1 && y >= region.y - 1 && y <= region.w + 1 {
+            return vec4(0.25, 0.25, 0.25, 1.0);
+        }
+    }
+
+    // Brush preview: the outline of the brush shape around the cursor
+    let p = cell + 0.5;
+    if in_brush(p, uniforms.hover_pos) && !(in_brush(p + vec2(1.0, 0.0), uniforms.hover_pos)
+        && in_brush(p - vec2(1.0, 0.0), uniforms.hover_pos)
+        && in_brush(p + vec2(0.0, 1.0), uniforms.hover_pos)
+        && in_brush(p - vec2(0.0, 1.0), uniforms.hover_pos)) {
+        return vec4(0.5, 0.5, 0.5, 1.0);
+    }
+
+    // The keyboard edit cursor gets a cyan ring, drawn inside the cell once cells are big enough
+    let edit = abs(vec2(x, y) - vec2<i32>(uniforms.edit_cursor));
+    let within = cell_pos - cell;
+    let inset = 2.0 / uniforms.zoom;
+    if (uniforms.zoom < 4.0 && max(edit.x, edit.y) == 1) || (uniforms.zoom >= 4.0 && all(edit == vec2(0))
+        && (any(within < vec2(inset)) || any(within > vec2(1.0 - inset)))) {
+        return vec4(0.0, 1.0, 1.0, 1.0);
+    }
+
+    // Outline the hovered cell with the ring of cells around it
+    let hover = abs(vec2(x, y) - vec2<i32>(floor(uniforms.hover_pos)));
+    // While snapping, a dot marks the center the brush is snapped to once cells are big enough
+    let to_center = cell_pos - uniforms.hover_pos;
+    if uniforms.snap == 1u && uniforms.zoom >= 4.0 && dot(to_center, to_center) < 0.02 {
+        return vec4(1.0, 1.0, 0.0, 1.0);
+    }
+    if max(hover.x, hover.y) == 1 {
+        return vec4(1.0, 1.0, 0.0, 1.0);
+    }
 
-    return vec4(textureLoad(texture, vec2(x, y), 0).rgb, 1.0);
+    // Paste preview: the outline of the clipboard with its top-left corner at the cursor
+    let paste = vec2(x, y) - vec2<i32>(floor(uniforms.hover_pos));
+    let paste_last = vec2<i32>(uniforms.paste_size) - 1;
+    if uniforms.paste_size.x > 0.0 && all(paste >= vec2(0)) && all(paste <= paste_last) {
+        var corner = vec2(0, 0);
+        switch uniforms.paste_corner {
+            case 1u: { corner = vec2(paste_last.x, 0); }
+            case 2u: { corner = paste_last; }
+            case 3u: { corner = vec2(0, paste_last.y); }
+            default: {}
+        }
+        if all(paste == corner) {
+            return vec4(1.0, 1.0, 1.0, 1.0);
+        }
+        if any(paste == vec2(0)) || any(paste == paste_last) {
+            return vec4(0.3, 0.6, 1.0, 1.0);
+        }
+    }
+
+    if uniforms.anchor.x >= 0.0 && on_line(vec2(x, y), vec2<i32>(uniforms.anchor), vec2<i32>(floor(uniforms.hover_pos))) {
+        return vec4(1.0, 0.5, 0.0, 1.0);
+    }
+
+    if uniforms.view_channel > 0u {
+        let value = textureLoad(texture, vec2(x, y), 0)[uniforms.view_channel - 1u];
+        return gridlines(vec4(vec3(value), 1.0), vec2(x, y), cell_pos - cell);
+    }
+
+    if uniforms.layers == 2u {
+        let first = textureLoad(texture, vec2(x, y), 0).rgb;
+        let second = textureLoad(second_texture, vec2(x, y), 0).rgb;
+        let color = vec3(f32(dot(first, first) > 0.5), f32(dot(second, second) > 0.5), 0.0);
+        return gridlines(styled(color, cell_pos - cell), vec2(x, y), cell_pos - cell);
+    }
+
+    if uniforms.show_changes == 1u {
+        // Compared with the board before the latest step, which may have been several generations
+        let texel = textureLoad(texture, vec2(x, y), 0);
+        let previous = textureLoad(previous_texture, vec2(x, y), 0);
+        let alive = dot(texel.rgb, texel.rgb) > 0.5;
+        var rgb = cell_color(texel) * 0.25;
+        if alive != (dot(previous.rgb, previous.rgb) > 0.5) {
+            rgb = vec3(1.0);
+        }
+        return gridlines(vec4(rgb, 1.0), vec2(x, y), cell_pos - cell);
+    }
+
+    if uniforms.smooth_zoom == 1u && uniforms.zoom > 1.0 {
+        // Several canvas pixels per cell, blended between the neighboring cells
+        let color = textureSampleLevel(display_texture, display_sampler, cell_pos / board, 0.0);
+        return glowing(gridlines(vec4(color.rgb, 1.0), vec2(x, y), cell_pos - cell), cell_pos / board);
+    }
+
+    if uniforms.filtered_zoom == 1u && uniforms.zoom < 1.0 {
+        // Several cells per canvas pixel, average them from the fitting mip level
+        let lod = log2(1.0 / uniforms.zoom);
+        let color = textureSampleLevel(display_texture, display_sampler, cell_pos / board, lod);
+        return glowing(gridlines(vec4(color.rgb, 1.0), vec2(x, y), cell_pos - cell), cell_pos / board);
+    }
+
+    let texel = textureLoad(texture, vec2(x, y), 0);
+    var rgb = cell_color(texel);
+    if uniforms.birth_animation_ms > 0.0 {
+        var shown = texel;
+        var scale = 0.0;
+        if dot(texel.rgb, texel.rgb) > 0.5 {
+            scale = grown(texel);
+        } else if uniforms.blend < 1.0 {
+            // In slow motion dying cells shrink away over the step
+            let previous = textureLoad(previous_texture, vec2(x, y), 0);
+            if dot(previous.rgb, previous.rgb) > 0.5 {
+                shown = previous;
+                scale = 1.0 - uniforms.blend;
+            }
+        }
+        if any(abs(cell_pos - cell - 0.5) > vec2(scale / 2.0)) {
+            shown = vec4(0.0, 0.0, 0.0, 1.0);
+        }
+        rgb = cell_color(shown);
+    } else if uniforms.blend < 1.0 {
+        let previous = cell_color(textureLoad(previous_texture, vec2(x, y), 0));
+        rgb = mix(previous, rgb, uniforms.blend);
+    }
+    if uniforms.predict == 1u && uniforms.sim_kind == 0u {
+        // Display only, the board itself is only ever advanced by `fs_compute`
+        let alive = dot(texel.rgb, texel.rgb) > 0.5;
+        let next = alive_next(x, y);
+        if alive && !next {
+            rgb = mix(rgb, uniforms.dying_color.rgb, uniforms.dying_color.a);
+        } else if !alive && next {
+            rgb = mix(rgb, uniforms.born_color.rgb, uniforms.born_color.a);
+        }
+    }
+    if uniforms.classified == 1u {
+        // Dead cells an oscillator passes through are tinted too, showing what it covers
+        let kind = textureLoad(classes, vec2(x, y), 0).r;
+        if kind > 0.75 {
+            rgb = mix(rgb, vec3(1.0, 0.6, 0.2), 0.6);
+        } else if kind > 0.25 {
+            rgb = mix(rgb, vec3(0.3, 0.5, 1.0), 0.6);
+        }
+    }
+    let color = styled(rgb, cell_pos - cell);
+    return glowing(gridlines(color, vec2(x, y), cell_pos - cell), cell_pos / board);
+}
+
+@fragment
+fn fs_clear(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4(0.0, 0.0, 0.0, 1.0);
+}
+
+// Writes the fragment position of every texel, to check the fullscreen triangle against
+@fragment
+fn fs_coordinates(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4(in.clip_position.xy, 0.0, 1.0);
 }
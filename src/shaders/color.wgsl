@@ -0,0 +1,49 @@
+// How cells are colored and shaped on the canvas
+
+// The color a cell is shown in, through the palette if there is one
+fn cell_color(texel: vec4<f32>) -> vec3<f32> {
+    if uniforms.palette_len == 0u {
+        return texel.rgb;
+    }
+    var index = 0u;
+    if dot(texel.rgb, texel.rgb) > 0.5 {
+        index = min(u32(max(texel.a, 1.0)), uniforms.palette_len - 1u);
+    }
+    return textureLoad(palette, vec2(i32(index), 0), 0).rgb;
+}
+
+// Fraction of its full size a live cell is drawn at by the birth animation, from the generations
+// it has been alive for, which are counted in the alpha channel
+fn grown(texel: vec4<f32>) -> f32 {
+    let age_ms = (max(texel.a, 1.0) - 1.0) * uniforms.ms_per_generation + uniforms.since_step_ms;
+    return clamp(age_ms / uniforms.birth_animation_ms, 0.0, 1.0);
+}
+
+// Shapes a cell's color according to the cell style, `within` is the position inside the cell
+fn styled(color: vec3<f32>, within: vec2<f32>) -> vec4<f32> {
+    if uniforms.cell_style == 1u {
+        // Fade over one canvas pixel at the circle's edge
+        let aa = 1.0 / uniforms.zoom;
+        let coverage = 1.0 - smoothstep(0.5 - aa, 0.5, length(within - 0.5));
+        return vec4(color * coverage, 1.0);
+    }
+    return vec4(color, 1.0);
+}
+
+// Lines along the top and left edge of each cell, twice as thick every `major_grid_interval` cells
+fn gridlines(color: vec4<f32>, cell: vec2<i32>, within: vec2<f32>) -> vec4<f32> {
+    let interval = i32(uniforms.major_grid_interval);
+    if interval == 0 {
+        return color;
+    }
+    // Width of one canvas pixel in cells
+    let pixel = 1.0 / uniforms.zoom;
+    let major = (cell % interval == vec2(0)) & (within < vec2(2.0 * pixel));
+    if any(major) {
+        return vec4(mix(color.rgb, uniforms.major_grid_color.rgb, uniforms.major_grid_color.a), 1.0);
+    }
+    if uniforms.zoom >= 4.0 && any(within < vec2(pixel)) {
+        return vec4(mix(color.rgb, uniforms.minor_grid_color.rgb, uniforms.minor_grid_color.a), 1.0);
+    }
+    return color;
+}
@@ -0,0 +1,21 @@
+// A rule written by the user: the lines between `//!rule` and `//!end` are replaced at runtime
+// with the body supplied through `data-rule-wgsl` or `set_rule_wgsl`. It returns the next texel
+// of the cell at (`x`, `y`) from
+// - `cell`: its current texel, rgb its color, alive if longer than 0.71, and a its age
+// - `sum`: the summed colors of its neighbors, the eight around or the four orthogonal ones
+// - `count`: how many of them are alive, -1 if the colors do not add up to a whole number
+// - `generation`: the generation being computed
+// Live cells should be unit length colors, so the neighbor counts of the next generation work.
+// Without a replacement it is Conway's Life.
+fn custom_rule(x: i32, y: i32, cell: vec4<f32>, sum: vec3<f32>, count: i32, generation: u32) -> vec4<f32> {
+//!rule
+    let alive = dot(cell.rgb, cell.rgb) > 0.5;
+    if !alive && count == 3 {
+        return vec4(normalize(sum), 1.0);
+    }
+    if alive && (count == 2 || count == 3) {
+        return vec4(cell.rgb, min(cell.a + 1.0, f32(MAX_AGE)));
+    }
+    return vec4(0.0, 0.0, 0.0, 1.0);
+//!end
+}
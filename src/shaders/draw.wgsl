@@ -0,0 +1,66 @@
+// The brush and the pass that paints it into the board
+
+fn in_brush(p: vec2<f32>, center: vec2<f32>) -> bool {
+    let diff = p - center;
+    let r = uniforms.brush_radius * uniforms.brush_scale;
+    switch uniforms.brush_shape {
+        case 1u: {
+            return max(abs(diff.x), abs(diff.y)) <= r;
+        }
+        case 2u: {
+            let drag = uniforms.hover_pos - uniforms.prev_mouse_pos;
+            var dir = vec2(1.0, 0.0);
+            if dot(drag, drag) > 0.0 {
+                dir = normalize(drag);
+            }
+            let along = dot(diff, dir);
+            let across = dot(diff, vec2(-dir.y, dir.x));
+            return abs(along) <= r && abs(across) <= 0.75;
+        }
+        default: {
+            return dot(diff, diff) < r * r;
+        }
+    }
+}
+
+// Like `in_brush`, but also true for cells covered by the brush continued past the opposite edge
+// when the brush wraps around
+fn in_wrapped_brush(p: vec2<f32>, center: vec2<f32>) -> bool {
+    let wraps = uniforms.brush_wrap == 1u || (uniforms.brush_wrap == 0u && uniforms.boundary_mode == 0u);
+    if !wraps {
+        return in_brush(p, center);
+    }
+    let size = uniforms.board_size;
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            if in_brush(p + vec2(f32(dx), f32(dy)) * size, center) {
+                return true;
+            }
+        }
+    }
+    return false;
+}
+
+// Runs after the frame's generations: paints the brush with a color picked per stroke and leaves
+// every other cell untouched. A spraying brush only sets a random few of its cells per frame.
+@fragment
+fn fs_draw(in: VertexOutput) -> @location(0) vec4<f32> {
+    let x = i32(in.clip_position.x);
+    let y = i32(in.clip_position.y);
+    if !in_active_region(x, y) || !in_wrapped_brush(in.clip_position.xy, uniforms.mouse_pos) {
+        discard;
+    }
+    if uniforms.brush_density < 1.0 {
+        // Kept small, the hash loses precision for large inputs
+        let frame = f32(uniforms.frame % 1024u);
+        let spray = random(in.clip_position.xy / uniforms.board_size + vec2(frame * 0.618, frame * 0.382));
+        if spray >= uniforms.brush_density {
+            discard;
+        }
+    }
+
+    let r = vec3(random(uniforms.seed), random(2.0 * uniforms.seed), random(3.0 * uniforms.seed));
+    let rlength = length(r);
+
+    return vec4(r / rlength, 1.0);
+}
@@ -0,0 +1,54 @@
+// Reading the neighbors of a cell across the edges of the board
+
+// The texel of the cell at `p`, cells with an infinite or NaN channel read as dead so that a bad
+// value can not spread over the board. Checked on the bits, the float comparisons may assume
+// there are no NaNs
+fn load_cell(p: vec2<i32>) -> vec4<f32> {
+    let texel = textureLoad(texture, p, 0);
+    let exponents = bitcast<vec4<u32>>(texel) & vec4(0x7f800000u);
+    if any(exponents == vec4(0x7f800000u)) {
+        return vec4(0.0, 0.0, 0.0, 1.0);
+    }
+    return texel;
+}
+
+// The cell at (`x`, `y`), which may be past the edges of the board, according to the boundary
+// mode
+fn neighbor(x: i32, y: i32) -> vec3<f32> {
+    let size = vec2<i32>(textureDimensions(texture));
+    var p = vec2(x, y);
+    switch uniforms.boundary_mode {
+        case 1u: {
+            if any(p < vec2(0)) || any(p >= size) {
+                return vec3(0.0);
+            }
+        }
+        case 2u: {
+            // The edge row itself is the mirror, so -1 reads 0 and size reads size - 1
+            p = select(p, -p - 1, p < vec2(0));
+            p = select(p, 2 * size - p - 1, p >= size);
+            p = clamp(p, vec2(0), size - 1);
+        }
+        default: {
+            p = ((p % size) + size) % size;
+        }
+    }
+    return load_cell(p).rgb;
+}
+
+// The colors of the neighbors of (`x`, `y`) added up: all eight cells around it, or only the four
+// orthogonal ones in the von Neumann neighborhood
+fn neighbor_sum(x: i32, y: i32) -> vec3<f32> {
+    var sum = vec3(0.0, 0.0, 0.0);
+    sum += neighbor(x - 1, y);
+    sum += neighbor(x + 1, y);
+    sum += neighbor(x, y + 1);
+    sum += neighbor(x, y - 1);
+    if uniforms.neighborhood == 0u {
+        sum += neighbor(x - 1, y - 1);
+        sum += neighbor(x - 1, y + 1);
+        sum += neighbor(x + 1, y - 1);
+        sum += neighbor(x + 1, y + 1);
+    }
+    return sum;
+}
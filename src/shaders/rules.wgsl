@@ -0,0 +1,107 @@
+// The rules that decide the next generation of a cell
+
+// Bitmasks over the live neighbor count
+struct Rule {
+    birth: u32,
+    survive: u32,
+};
+
+// The neighbor count encoded in the length of the summed neighbor colors, or -1 if the length is
+// too far from a whole number because differently colored neighbors point in different directions
+fn neighbor_count(sumlength: f32) -> i32 {
+    let n = round(sumlength);
+    if abs(sumlength - n) > 0.1 {
+        return -1;
+    }
+    return i32(n);
+}
+
+fn rule_allows(mask: u32, count: i32) -> bool {
+    return count >= 0 && ((mask >> u32(count)) & 1u) == 1u;
+}
+
+fn sigma1(x: f32, a: f32, alpha: f32) -> f32 {
+    return 1.0 / (1.0 + exp(-(x - a) * 4.0 / alpha));
+}
+
+fn sigma2(x: f32, a: f32, b: f32, alpha: f32) -> f32 {
+    return sigma1(x, a, alpha) * (1.0 - sigma1(x, b, alpha));
+}
+
+fn sigma_m(x: f32, y: f32, m: f32, alpha: f32) -> f32 {
+    let w = sigma1(m, 0.5, alpha);
+    return x * (1.0 - w) + y * w;
+}
+
+// Rafler's SmoothLife: the new state follows from how filled the cell's disk (m) and the
+// surrounding annulus (n) are, with smooth thresholds instead of neighbor counts
+fn smooth_life(x: i32, y: i32) -> vec4<f32> {
+    let ri = uniforms.inner_radius;
+    let ra = uniforms.outer_radius;
+    let r = i32(ceil(min(ra, 12.0)));
+
+    var inner = 0.0;
+    var inner_area = 0.0;
+    var outer = 0.0;
+    var outer_area = 0.0;
+    for (var dy = -r; dy <= r; dy++) {
+        for (var dx = -r; dx <= r; dx++) {
+            let d = length(vec2(f32(dx), f32(dy)));
+            if d >= ra {
+                continue;
+            }
+            let value = min(length(neighbor(x + dx, y + dy)), 1.0);
+            if d < ri {
+                inner += value;
+                inner_area += 1.0;
+            } else {
+                outer += value;
+                outer_area += 1.0;
+            }
+        }
+    }
+    let m = inner / max(inner_area, 1.0);
+    let n = outer / max(outer_area, 1.0);
+
+    let alpha_n = uniforms.sigmoid_width;
+    let alpha_m = alpha_n * 5.25;
+    let state = sigma2(
+        n,
+        sigma_m(0.278, 0.267, m, alpha_m),
+        sigma_m(0.365, 0.445, m, alpha_m),
+        alpha_n
+    );
+
+    return vec4(vec3(clamp(state, 0.0, 1.0) / sqrt(3.0)), 1.0);
+}
+
+// Larger than Life: a totalistic rule over every cell within `neighbor_radius` in each direction,
+// the neighbor count is the number of live cells and no longer encoded in the summed colors
+fn larger_than_life(x: i32, y: i32) -> vec4<f32> {
+    let r = i32(min(uniforms.neighbor_radius, 10u));
+
+    var count = 0u;
+    var sum = vec3(0.0, 0.0, 0.0);
+    for (var dy = -r; dy <= r; dy++) {
+        for (var dx = -r; dx <= r; dx++) {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let cell = neighbor(x + dx, y + dy);
+            if dot(cell, cell) > 0.5 {
+                count += 1u;
+                sum += cell;
+            }
+        }
+    }
+
+    let current = load_cell(vec2(x, y)).rgb;
+    if dot(current, current) > 0.5 {
+        if count >= uniforms.min_survive && count <= uniforms.max_survive {
+            return vec4(current, 1.0);
+        }
+    } else if count >= uniforms.min_birth && count <= uniforms.max_birth && dot(sum, sum) > 0.0 {
+        return vec4(normalize(sum), 1.0);
+    }
+    return vec4(0.0, 0.0, 0.0, 1.0);
+}
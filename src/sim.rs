@@ -0,0 +1,389 @@
+//! A plain CPU implementation of the classic rules the shader runs, slow but easy to check by
+//! hand, for comparing the shader against and for running boards without a GPU.
+
+/// How cells past the edges of the board are read, the same modes as the shader's
+/// `boundary_mode`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Boundary {
+    /// The board wraps around, the cell past the right edge is the leftmost one
+    Torus,
+    /// Everything past the edges is dead
+    Dead,
+    /// The edge rows and columns are mirrored, the cell past the right edge is the rightmost one
+    Reflect,
+}
+
+impl Boundary {
+    /// Where `coordinate`, which may be past the edges, is read from along an axis of `size`
+    /// cells, `None` for dead cells.
+    pub fn resolve(self, coordinate: i64, size: i64) -> Option<i64> {
+        match self {
+            Boundary::Torus => Some(coordinate.rem_euclid(size)),
+            Boundary::Dead if coordinate < 0 || coordinate >= size => None,
+            Boundary::Dead => Some(coordinate),
+            // The edge row itself is the mirror, so -1 reads 0 and size reads size - 1
+            Boundary::Reflect if coordinate < 0 => Some((-coordinate - 1).min(size - 1)),
+            Boundary::Reflect if coordinate >= size => Some((2 * size - coordinate - 1).max(0)),
+            Boundary::Reflect => Some(coordinate),
+        }
+    }
+}
+
+/// Which cells around a cell are its neighbors, the same as the shader's `neighborhood`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// The eight cells around
+    Moore,
+    /// The four cells sharing an edge
+    VonNeumann,
+}
+
+/// Life-like rules as bit masks of the neighbor counts, bit 3 of `birth` set means a dead cell
+/// with three live neighbors comes alive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub birth: u32,
+    pub survive: u32,
+    pub boundary: Boundary,
+    pub neighborhood: Neighborhood,
+    /// Cells this close to the edges are killed every generation, 0 keeps them
+    pub kill_margin: u32,
+}
+
+impl Rule {
+    /// B3/S23 with the Moore neighborhood on a wrapping board without a kill margin.
+    pub const CONWAY: Rule = Rule {
+        birth: 1 << 3,
+        survive: 1 << 2 | 1 << 3,
+        boundary: Boundary::Torus,
+        neighborhood: Neighborhood::Moore,
+        kill_margin: 0,
+    };
+}
+
+/// What a group of cells on a repeating board does over its period.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    /// None of its cells ever change
+    StillLife,
+    /// Some of its cells die and come back
+    Oscillator,
+}
+
+/// Cells of a repeating board that touch each other in some generation of its period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    pub kind: Kind,
+    /// Every cell alive in some generation of the period
+    pub cells: Vec<(u32, u32)>,
+}
+
+/// A board of cells stored row by row, 0 for dead and anything else for alive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<u8>,
+}
+
+impl Board {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![0; (width * height) as usize],
+        }
+    }
+
+    pub fn alive(&self, x: u32, y: u32) -> bool {
+        self.cells[(y * self.width + x) as usize] != 0
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, alive: bool) {
+        self.cells[(y * self.width + x) as usize] = alive as u8;
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&cell| cell != 0).count()
+    }
+
+    /// The cell at (`x`, `y`), which may be past the edges, according to `boundary`.
+    fn neighbor(&self, x: i64, y: i64, boundary: Boundary) -> bool {
+        match (
+            boundary.resolve(x, self.width as i64),
+            boundary.resolve(y, self.height as i64),
+        ) {
+            (Some(x), Some(y)) => self.alive(x as u32, y as u32),
+            _ => false,
+        }
+    }
+
+    /// Counts the live cells among the neighbors of (`x`, `y`).
+    pub fn neighbors(&self, x: u32, y: u32, rule: Rule) -> u32 {
+        let mut count = 0;
+        for dy in -1i64..=1 {
+            for dx in -1i64..=1 {
+                let diagonal = dx != 0 && dy != 0;
+                if (dx, dy) == (0, 0) || (diagonal && rule.neighborhood == Neighborhood::VonNeumann)
+                {
+                    continue;
+                }
+                if self.neighbor(x as i64 + dx, y as i64 + dy, rule.boundary) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// The next generation under `rule`.
+    pub fn step(&self, rule: Rule) -> Self {
+        let mut next = Self::new(self.width, self.height);
+        let margin = rule.kill_margin;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if x < margin || y < margin || x + margin >= self.width || y + margin >= self.height
+                {
+                    continue;
+                }
+                let mask = if self.alive(x, y) {
+                    rule.survive
+                } else {
+                    rule.birth
+                };
+                let count = self.neighbors(x, y, rule);
+                next.set(x, y, (mask >> count) & 1 == 1);
+            }
+        }
+        next
+    }
+
+    /// Advances `generations` generations under `rule`.
+    pub fn run(&self, rule: Rule, generations: u32) -> Self {
+        (0..generations).fold(self.clone(), |board, _| board.step(rule))
+    }
+
+    /// The smallest number of generations up to `max_period` after which the board is back to
+    /// how it is now, 1 for a still life, or 0 if it keeps changing for longer, e.g. because it
+    /// grows or something moves across it.
+    pub fn period(&self, rule: Rule, max_period: u32) -> u32 {
+        let mut board = self.clone();
+        for period in 1..=max_period {
+            board = board.step(rule);
+            if board == *self {
+                return period;
+            }
+        }
+        0
+    }
+
+    /// Splits a board that repeats every `period` generations under `rule` into groups of cells
+    /// that are alive in some generation and touch each other, diagonally or orthogonally, in
+    /// the overlay of all of them. Nothing is looked up past the edges, so leave a dead border.
+    pub fn components(&self, rule: Rule, period: u32) -> Vec<Component> {
+        let size = self.cells.len();
+        let mut ever_alive = vec![false; size];
+        let mut changes = vec![false; size];
+        let mut board = self.clone();
+        for _ in 0..period {
+            for i in 0..size {
+                ever_alive[i] |= board.cells[i] != 0;
+                changes[i] |= (board.cells[i] != 0) != (self.cells[i] != 0);
+            }
+            board = board.step(rule);
+        }
+
+        let mut seen = vec![false; size];
+        let mut components = Vec::new();
+        for start in 0..size {
+            if !ever_alive[start] || seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut stack = vec![start];
+            let mut component = Component {
+                kind: Kind::StillLife,
+                cells: Vec::new(),
+            };
+            while let Some(i) = stack.pop() {
+                let (x, y) = (i as u32 % self.width, i as u32 / self.width);
+                component.cells.push((x, y));
+                if changes[i] {
+                    component.kind = Kind::Oscillator;
+                }
+                for ny in y.saturating_sub(1)..=(y + 1).min(self.height - 1) {
+                    for nx in x.saturating_sub(1)..=(x + 1).min(self.width - 1) {
+                        let j = (ny * self.width + nx) as usize;
+                        if ever_alive[j] && !seen[j] {
+                            seen[j] = true;
+                            stack.push(j);
+                        }
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width` x `height` board with the cells marked `O` in `rows` alive, the rows placed at
+    /// (`x`, `y`).
+    fn board(width: u32, height: u32, x: u32, y: u32, rows: &[&str]) -> Board {
+        let mut board = Board::new(width, height);
+        for (dy, row) in rows.iter().enumerate() {
+            for (dx, cell) in row.chars().enumerate() {
+                board.set(x + dx as u32, y + dy as u32, cell == 'O');
+            }
+        }
+        board
+    }
+
+    fn rule(birth: &[u32], survive: &[u32]) -> Rule {
+        Rule {
+            birth: birth.iter().map(|n| 1 << n).sum(),
+            survive: survive.iter().map(|n| 1 << n).sum(),
+            ..Rule::CONWAY
+        }
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let horizontal = board(5, 5, 1, 2, &["OOO"]);
+        let vertical = board(5, 5, 2, 1, &["O", "O", "O"]);
+        assert_eq!(horizontal.step(Rule::CONWAY), vertical);
+        assert_eq!(vertical.step(Rule::CONWAY), horizontal);
+        assert_eq!(horizontal.period(Rule::CONWAY, 10), 2);
+    }
+
+    #[test]
+    fn block_is_a_still_life() {
+        let block = board(4, 4, 1, 1, &["OO", "OO"]);
+        assert_eq!(block.step(Rule::CONWAY), block);
+        assert_eq!(block.period(Rule::CONWAY, 10), 1);
+    }
+
+    #[test]
+    fn glider_moves_diagonally() {
+        let glider = [".O.", "..O", "OOO"];
+        let start = board(8, 8, 1, 1, &glider);
+        assert_eq!(start.run(Rule::CONWAY, 4), board(8, 8, 2, 2, &glider));
+        assert_eq!(start.population(), 5);
+        assert_eq!(start.period(Rule::CONWAY, 10), 0);
+    }
+
+    #[test]
+    fn glider_wraps_around_the_torus() {
+        let glider = [".O.", "..O", "OOO"];
+        let start = board(6, 6, 0, 0, &glider);
+        // A glider moves one cell per 4 generations, so after 24 it is back where it started
+        assert_eq!(start.run(Rule::CONWAY, 24), start);
+    }
+
+    #[test]
+    fn glider_dies_against_dead_edges() {
+        let dead = Rule {
+            boundary: Boundary::Dead,
+            ..Rule::CONWAY
+        };
+        let start = board(6, 6, 0, 0, &[".O.", "..O", "OOO"]);
+        // It turns into a block in the corner
+        let settled = start.run(dead, 24);
+        assert_eq!(settled, board(6, 6, 4, 4, &["OO", "OO"]));
+    }
+
+    #[test]
+    fn boundaries_read_past_the_edges() {
+        let mut board = Board::new(3, 3);
+        board.set(0, 1, true);
+        let count = |boundary| {
+            board.neighbors(
+                2,
+                1,
+                Rule {
+                    boundary,
+                    ..Rule::CONWAY
+                },
+            )
+        };
+        assert_eq!(count(Boundary::Torus), 1);
+        assert_eq!(count(Boundary::Dead), 0);
+        assert_eq!(count(Boundary::Reflect), 0);
+
+        let mut edge = Board::new(3, 3);
+        edge.set(0, 0, true);
+        let reflect = Rule {
+            boundary: Boundary::Reflect,
+            ..Rule::CONWAY
+        };
+        // (0, 0) is mirrored to (-1, -1), (-1, 0) and (0, -1) around the corner
+        assert_eq!(edge.neighbors(0, 0, reflect), 3);
+        assert_eq!(edge.neighbors(1, 0, reflect), 2);
+    }
+
+    #[test]
+    fn resolve_maps_coordinates_past_the_edges() {
+        assert_eq!(Boundary::Torus.resolve(-1, 8), Some(7));
+        assert_eq!(Boundary::Torus.resolve(8, 8), Some(0));
+        assert_eq!(Boundary::Dead.resolve(-1, 8), None);
+        assert_eq!(Boundary::Dead.resolve(8, 8), None);
+        assert_eq!(Boundary::Reflect.resolve(-1, 8), Some(0));
+        assert_eq!(Boundary::Reflect.resolve(8, 8), Some(7));
+        assert_eq!(Boundary::Reflect.resolve(3, 8), Some(3));
+    }
+
+    #[test]
+    fn highlife_births_on_six() {
+        let highlife = rule(&[3, 6], &[2, 3]);
+        // The center has six live neighbors and none of them survive or get born under B3/S23
+        let six = board(5, 5, 1, 1, &["OOO", "...", "OOO"]);
+        assert!(six.step(highlife).alive(2, 2));
+        assert!(!six.step(Rule::CONWAY).alive(2, 2));
+    }
+
+    #[test]
+    fn von_neumann_ignores_diagonals() {
+        let von_neumann = Rule {
+            neighborhood: Neighborhood::VonNeumann,
+            ..Rule::CONWAY
+        };
+        let diagonals = board(3, 3, 0, 0, &["O.O", "...", "O.O"]);
+        assert_eq!(diagonals.neighbors(1, 1, Rule::CONWAY), 4);
+        assert_eq!(diagonals.neighbors(1, 1, von_neumann), 0);
+
+        let orthogonal = board(5, 5, 1, 1, &[".O.", "O.O", "..."]);
+        assert_eq!(orthogonal.neighbors(2, 2, von_neumann), 3);
+        assert!(orthogonal.step(von_neumann).alive(2, 2));
+    }
+
+    #[test]
+    fn kill_margin_clears_the_edges() {
+        let margin = Rule {
+            kill_margin: 1,
+            ..Rule::CONWAY
+        };
+        let block = board(4, 4, 0, 0, &["OO", "OO"]);
+        let next = block.step(margin);
+        assert!(!next.alive(0, 0));
+        assert!(next.alive(1, 1));
+    }
+
+    #[test]
+    fn components_tell_still_lifes_from_oscillators() {
+        let mut start = board(12, 7, 1, 1, &["OO", "OO"]);
+        for x in 6..9 {
+            start.set(x, 3, true);
+        }
+        let components = start.components(Rule::CONWAY, 2);
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0].kind, Kind::StillLife);
+        assert_eq!(components[0].cells.len(), 4);
+        assert_eq!(components[1].kind, Kind::Oscillator);
+        // The horizontal and the vertical phase share the center
+        assert_eq!(components[1].cells.len(), 5);
+    }
+}
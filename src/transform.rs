@@ -0,0 +1,95 @@
+//! Rotating and mirroring rectangles of cells stored row by row, `texel` bytes per cell, like the
+//! texels read back from the board.
+
+/// Rotates a `width` by `height` rectangle by 90° clockwise, the result is `height` wide and
+/// `width` high.
+pub fn rotate_clockwise(data: &[u8], width: u32, height: u32, texel: usize) -> Vec<u8> {
+    let mut rotated = Vec::with_capacity(data.len());
+    // Row y of the result is column y of the original, read from the bottom up
+    for y in 0..width as usize {
+        for x in 0..height as usize {
+            let source = (height as usize - 1 - x) * width as usize + y;
+            rotated.extend_from_slice(&data[source * texel..(source + 1) * texel]);
+        }
+    }
+    rotated
+}
+
+/// Mirrors a `width` by `height` rectangle left to right.
+pub fn flip_horizontal(data: &[u8], width: u32, height: u32, texel: usize) -> Vec<u8> {
+    let mut flipped = Vec::with_capacity(data.len());
+    for y in 0..height as usize {
+        for x in (0..width as usize).rev() {
+            let source = y * width as usize + x;
+            flipped.extend_from_slice(&data[source * texel..(source + 1) * texel]);
+        }
+    }
+    flipped
+}
+
+/// Mirrors a `width` by `height` rectangle top to bottom.
+pub fn flip_vertical(data: &[u8], width: u32, height: u32, texel: usize) -> Vec<u8> {
+    let row = width as usize * texel;
+    data.chunks(row)
+        .take(height as usize)
+        .rev()
+        .flatten()
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes per cell, more than one so a transform that splits texels shows up
+    const TEXEL: usize = 2;
+
+    /// An L, 3 wide and 2 high, that looks different after every rotation and mirror.
+    const L: [&str; 2] = ["O..", "OOO"];
+
+    /// The cells marked `O` in `rows` as texels of two different bytes, the others zero.
+    fn cells(rows: &[&str]) -> Vec<u8> {
+        rows.iter()
+            .flat_map(|row| row.chars())
+            .flat_map(|cell| if cell == 'O' { [1, 2] } else { [0, 0] })
+            .collect()
+    }
+
+    #[test]
+    fn rotating_turns_clockwise() {
+        let rotated = rotate_clockwise(&cells(&L), 3, 2, TEXEL);
+        assert_eq!(rotated, cells(&["OO", "O.", "O."]));
+    }
+
+    #[test]
+    fn rotating_four_times_is_identity() {
+        let mut data = cells(&L);
+        let (mut width, mut height) = (3, 2);
+        for _ in 0..4 {
+            data = rotate_clockwise(&data, width, height, TEXEL);
+            (width, height) = (height, width);
+        }
+        assert_eq!(data, cells(&L));
+    }
+
+    #[test]
+    fn flipping_mirrors() {
+        assert_eq!(
+            flip_horizontal(&cells(&L), 3, 2, TEXEL),
+            cells(&["..O", "OOO"])
+        );
+        assert_eq!(
+            flip_vertical(&cells(&L), 3, 2, TEXEL),
+            cells(&["OOO", "O.."])
+        );
+    }
+
+    #[test]
+    fn flipping_twice_is_identity() {
+        let flipped = flip_horizontal(&cells(&L), 3, 2, TEXEL);
+        assert_eq!(flip_horizontal(&flipped, 3, 2, TEXEL), cells(&L));
+        let flipped = flip_vertical(&cells(&L), 3, 2, TEXEL);
+        assert_eq!(flip_vertical(&flipped, 3, 2, TEXEL), cells(&L));
+    }
+}
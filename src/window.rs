@@ -1,9 +1,15 @@
-use log::warn;
+use crate::library;
+use crate::pattern::{Format, Pattern};
+use crate::shader;
+use crate::sim;
+use crate::transform;
+use log::{debug, error, warn};
 use raw_window_handle::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, WebDisplayHandle,
     WebWindowHandle,
 };
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::RwLock;
@@ -28,110 +34,877 @@ unsafe impl HasRawWindowHandle for WebWindow {
 struct Uniforms {
     mouse_pos: [f32; 2],
     seed: [f32; 2],
+    selection: [f32; 4],
+    active_region: [f32; 4],
+    layers: u32,
+    _padding: u32,
+    hover_pos: [f32; 2],
+    prev_mouse_pos: [f32; 2],
+    brush_radius: f32,
+    brush_shape: u32,
+    sim_kind: u32,
+    inner_radius: f32,
+    outer_radius: f32,
+    sigmoid_width: f32,
+    pan: [f32; 2],
+    zoom: f32,
+    minimap: u32,
+    cell_style: u32,
+    neighbor_radius: u32,
+    min_birth: u32,
+    max_birth: u32,
+    min_survive: u32,
+    max_survive: u32,
+    major_grid_interval: u32,
+    _padding2: u32,
+    minor_grid_color: [f32; 4],
+    major_grid_color: [f32; 4],
+    edit_cursor: [f32; 2],
+    filtered_zoom: u32,
+    view_channel: u32,
+    kill_margin: u32,
+    palette_len: u32,
+    canvas_size: [f32; 2],
+    boundary_mode: u32,
+    brush_density: f32,
+    frame: u32,
+    time: f32,
+    counter_corner: u32,
+    generation: u32,
+    brush_wrap: u32,
+    _padding6: u32,
+    board_size: [f32; 2],
+    blend: f32,
+    birth_animation_ms: f32,
+    ms_per_generation: f32,
+    since_step_ms: f32,
+    snap: u32,
+    _padding7: u32,
+    dying_color: [f32; 4],
+    born_color: [f32; 4],
+    predict: u32,
+    show_changes: u32,
+    anchor: [f32; 2],
+    smooth_zoom: u32,
+    neighborhood: u32,
+    paste_size: [f32; 2],
+    paste_corner: u32,
+    update_scheme: u32,
+    phase: u32,
+    glow: u32,
+    glow_radius: f32,
+    glow_intensity: f32,
+    brush_scale: f32,
+    classified: u32,
+    content_offset: [f32; 2],
+    scrolling: u32,
+    brick: u32,
 }
 
 impl Uniforms {
     fn new() -> Self {
         Self {
             mouse_pos: [-1000.0, 0.0],
-            seed: [0.0, 0.0],
+            seed: [-1000.0, 0.0],
+            selection: SELECTION_INACTIVE,
+            active_region: [0.0, 0.0, 1023.0, 1023.0],
+            layers: 1,
+            _padding: 0,
+            hover_pos: [-1000.0, 0.0],
+            prev_mouse_pos: [-1000.0, 0.0],
+            brush_radius: 11.0,
+            brush_shape: BrushShape::Circle as u32,
+            sim_kind: SimKind::Classic as u32,
+            inner_radius: 7.0 / 3.0,
+            outer_radius: 7.0,
+            sigmoid_width: 0.028,
+            pan: [0.0, 0.0],
+            zoom: 1.0,
+            minimap: 1,
+            cell_style: 0,
+            neighbor_radius: 1,
+            min_birth: 3,
+            max_birth: 3,
+            min_survive: 2,
+            max_survive: 3,
+            major_grid_interval: 0,
+            _padding2: 0,
+            minor_grid_color: [1.0, 1.0, 1.0, 0.08],
+            major_grid_color: [1.0, 1.0, 1.0, 0.35],
+            edit_cursor: [-1000.0, 0.0],
+            filtered_zoom: 1,
+            view_channel: 0,
+            kill_margin: 0,
+            palette_len: 0,
+            canvas_size: [1024.0, 1024.0],
+            boundary_mode: BOUNDARY_TORUS,
+            brush_density: 1.0,
+            frame: 0,
+            time: 0.0,
+            counter_corner: 0,
+            generation: 0,
+            brush_wrap: BRUSH_WRAP_AUTO,
+            _padding6: 0,
+            board_size: [1024.0, 1024.0],
+            blend: 1.0,
+            birth_animation_ms: 0.0,
+            ms_per_generation: 0.0,
+            since_step_ms: 0.0,
+            snap: 0,
+            _padding7: 0,
+            dying_color: [1.0, 0.3, 0.2, 0.6],
+            born_color: [0.2, 1.0, 0.4, 0.6],
+            predict: 0,
+            show_changes: 0,
+            anchor: [-1000.0, 0.0],
+            smooth_zoom: 0,
+            neighborhood: NEIGHBORHOOD_MOORE,
+            paste_size: [0.0; 2],
+            paste_corner: 0,
+            update_scheme: UPDATE_SYNCHRONOUS,
+            phase: 0,
+            glow: 0,
+            glow_radius: 4.0,
+            glow_intensity: 0.8,
+            brush_scale: 1.0,
+            classified: 0,
+            content_offset: [0.0; 2],
+            scrolling: 0,
+            brick: 0,
         }
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BrushShape {
+    Circle = 0,
+    Square = 1,
+    /// A thin stroke across the brush diameter, oriented along the drag direction
+    Line = 2,
+}
+
+impl BrushShape {
+    fn next(self) -> Self {
+        match self {
+            BrushShape::Circle => BrushShape::Square,
+            BrushShape::Square => BrushShape::Line,
+            BrushShape::Line => BrushShape::Circle,
+        }
+    }
+}
+
+/// What a plain drag on the canvas does, Alt+drag does the other.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum InteractionMode {
+    Draw,
+    Pan,
+}
+
+impl InteractionMode {
+    fn css_class(self) -> &'static str {
+        match self {
+            InteractionMode::Draw => "mode-draw",
+            InteractionMode::Pan => "mode-pan",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SimKind {
+    /// Discrete Life with the layer's B/S rule
+    Classic = 0,
+    /// Continuous Life after Rafler, with cell states in [0, 1] stored as the length of the color
+    SmoothLife = 1,
+    /// The transition function supplied with `data-rule-wgsl` or `set_rule_wgsl`
+    Custom = 2,
+}
+
+/// Keeps the SmoothLife neighborhood loop, which runs for every cell, bounded.
+/// Must match the clamp in `smooth_life` in the shader.
+const MAX_SMOOTH_RADIUS: f32 = 12.0;
+/// Keeps the Larger than Life neighborhood loop bounded.
+/// Must match the clamp in `larger_than_life` in the shader.
+const MAX_NEIGHBOR_RADIUS: u32 = 10;
+
+const MAX_ZOOM: f32 = 64.0;
+/// Zooming out shows the whole board surrounded by background, down to a quarter of its size.
+const MIN_ZOOM: f32 = 0.25;
+/// Enough levels of the display copy to sample at `MIN_ZOOM`.
+const DISPLAY_MIP_LEVELS: u32 = 3;
+/// Level of the display copy that is blurred into the glow, a quarter of the board on each axis.
+const GLOW_LEVEL: usize = 2;
+/// Texels of the glow level the blur reaches on each side, every one is a texture read per pass.
+const MAX_GLOW_RADIUS: f32 = 16.0;
+/// WebGL pays more for every read, so the glow stays narrower there.
+const MAX_GLOW_RADIUS_WEBGL: f32 = 6.0;
+
+/// Keeps warming up a loaded pattern from freezing the page.
+const MAX_WARMUP: u32 = 10_000;
+
+/// Frames slower than this make turbo mode back off, faster ones let it ramp up. Above the
+/// 16.7ms between frames of a 60Hz display so frames in time with it leave room to ramp up.
+const FRAME_BUDGET_MS: f64 = 20.0;
+
+const INSPECTION_INTERVAL_MS: f64 = 100.0;
+
+/// Longer gaps between frames are stalls rather than slow frames.
+const MAX_FRAME_DELTA_MS: f64 = 250.0;
+
+/// How often the frame statistics below the canvas are refreshed while they are shown.
+const FRAME_STATS_INTERVAL_MS: f64 = 500.0;
+
+/// Formats the cell textures can be stored in, in order of preference. Both hold a color per cell,
+/// the half float one just with less precision.
+///
+/// Channels of a cell:
+/// - R, G, B: the color of a live cell as a unit vector, all zero for a dead cell. SmoothLife
+///   stores its continuous state `s` as `s / sqrt(3)` in each of them instead.
+/// - A: always 1, unused so far.
+const CELL_FORMATS: [wgpu::TextureFormat; 2] = [
+    wgpu::TextureFormat::Rgba32Float,
+    wgpu::TextureFormat::Rgba16Float,
+];
+
+/// Color of live cells loaded from pattern files, normalized like the brush colors.
+const PATTERN_COLOR: [f32; 4] = [0.57735026, 0.57735026, 0.57735026, 1.0];
+
+/// Color of dead cells and of everything drawn around the board.
+const BACKGROUND: wgpu::Color = wgpu::Color::BLACK;
+
+const SELECTION_INACTIVE: [f32; 4] = [-1.0, -1.0, -1.0, -1.0];
+
+/// Inclusive rectangle of texture cells: (x0, y0, x1, y1) with x0 <= x1 and y0 <= y1.
+type Selection = (u32, u32, u32, u32);
+
 struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
-    compute_pipeline: wgpu::RenderPipeline,
+    /// Its size follows the canvas, which follows the layout when the viewport changes
+    config: RwLock<wgpu::SurfaceConfiguration>,
+    /// Samples per pixel of the presentation pass, the simulation is always single sampled
+    msaa_samples: u32,
+    /// Multisampled target of the presentation pass that resolves into the surface, sized like
+    /// the surface and only there with `msaa_samples` above 1
+    msaa_view: RwLock<Option<wgpu::TextureView>>,
+    /// Replaced when a custom rule is compiled
+    compute_pipeline: RwLock<wgpu::RenderPipeline>,
+    compute_pipeline_layout: wgpu::PipelineLayout,
+    /// Body of `custom_rule` in the current compute pipeline, if one was supplied
+    custom_rule: RwLock<Option<String>>,
+    /// Moves the cells into the channels of the current `sim_kind` after switching to it
+    normalize_pipeline: wgpu::RenderPipeline,
     render_pipeline: wgpu::RenderPipeline,
+    clear_pipeline: wgpu::RenderPipeline,
+    /// Paints the brush into the board after the generations of a frame have been simulated
+    draw_pipeline: wgpu::RenderPipeline,
+    empty_bind_group: wgpu::BindGroup,
+    /// Fill the first level of `display_mips` from the cells and each further level from the
+    /// one before
+    mip_base_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    display_mips: DisplayMips,
+    /// Blur a level of `display_mips` into `glow` horizontally, then vertically
+    glow_horizontal_pipeline: wgpu::RenderPipeline,
+    glow_vertical_pipeline: wgpu::RenderPipeline,
+    glow: Glow,
+    paused: RwLock<bool>,
+    /// Generations simulated since startup
+    generation: RwLock<u64>,
+    /// Generation at which the simulation pauses itself, cleared once reached so that playing
+    /// again continues past it
+    max_generations: RwLock<Option<u64>>,
+    warmup: u32,
+    /// Generations per frame as requested, turbo mode raises it above one
+    target_steps_per_frame: RwLock<u32>,
+    /// Generations per frame right now, lowered below the target while frames are too slow
+    steps_per_frame: RwLock<u32>,
+    turbo_steps: u32,
+    /// Cells flipped by n
+    noise_flips: u32,
+    /// Generations between the stamps q schedules
+    stamp_interval: u64,
+    scheduled_stamps: RwLock<Vec<ScheduledStamp>>,
+    /// The pattern loaded last, q schedules it
+    last_pattern: RwLock<Option<Pattern>>,
+    /// `requestAnimationFrame` timestamp of the previous frame
+    last_frame_time: RwLock<Option<f64>>,
+    /// Milliseconds between the last two frames, 0 on the first frame and after stalls
+    frame_delta: RwLock<f64>,
+    frame_stats: RwLock<FrameStats>,
+    /// Frame rate and dropped frames below the canvas, toggled with F
+    frame_stats_output: web_sys::HtmlElement,
+    hidden: RwLock<bool>,
+    /// `serialize_settings` right after starting, what version 1 settings fall back to for the
+    /// fields they leave out
+    default_settings: String,
+    canvas: web_sys::HtmlCanvasElement,
+    tooltip: web_sys::HtmlElement,
+    /// Shows the speed over the corner of the canvas for a moment after Alt+wheel changed it, and
+    /// the progress of jumping ahead
+    badge: web_sys::HtmlElement,
+    /// Time until which `badge` stays, later changes push it back
+    badge_until: RwLock<f64>,
+    /// Every keyboard shortcut and what it is set to, toggled with ?
+    help: web_sys::HtmlElement,
+    /// What every binding was set to when `help` was last filled
+    help_states: RwLock<Vec<String>>,
+    /// Visually hidden, screen readers announce whatever is written into it
+    live_region: web_sys::HtmlElement,
+    /// Shows the result of the last benchmark below the canvas
+    benchmark_output: web_sys::HtmlElement,
+    /// Shown below the canvas once a running board has no live cells left
+    extinct_message: web_sys::HtmlElement,
+    /// Population over the last `population_window` generations, toggled with H
+    population_graph: web_sys::HtmlCanvasElement,
+    /// Generation and population of every sample the graph shows, oldest first
+    population_history: RwLock<VecDeque<(u64, u32)>>,
+    population_window: u64,
+    /// Pause as soon as the board is found extinct, `data-pause-on-extinction`
+    pause_on_extinction: bool,
+    /// Pause while the window is not focused, `data-pause-on-blur`
+    pause_on_blur: bool,
+    /// Pause while drawing so strokes only evolve once finished, `data-draw-pauses`
+    draw_pauses: bool,
+    /// Zoom in on loaded patterns leaving this fraction of the canvas around them,
+    /// `data-fit-pattern`
+    fit_pattern_margin: Option<f32>,
+    /// Cells around loaded patterns that are killed along with stamping them, so they start out
+    /// isolated from whatever is on the board. 0 leaves the surroundings alone
+    quiet_zone: RwLock<u32>,
+    /// The simulation was running when the window lost focus and resumes once it gets it back,
+    /// cleared by pausing or resuming by hand in between
+    blur_paused: RwLock<bool>,
+    /// The simulation was running when the current stroke started and resumes once it ends,
+    /// cleared by pausing or resuming by hand in between
+    draw_paused: RwLock<bool>,
+    benchmarking: RwLock<bool>,
+    /// Cell moved with the arrow keys and toggled with Enter or Space, hidden until used
+    edit_cursor: RwLock<Option<(u32, u32)>>,
+    /// Whether hovering shows the cell inspection tooltip
+    inspect: RwLock<bool>,
+    inspecting: RwLock<bool>,
+    last_inspection: RwLock<f64>,
+    searching: RwLock<bool>,
+    /// Set while `export_gif` steps the board, so the brush stays out of the recording
+    capturing_gif: RwLock<bool>,
+    gif_frames: u32,
+    gif_step: u32,
+    /// Length of videos recorded with D unless stopped early, `data-video-seconds`
+    video_seconds: f64,
+    /// Records the canvas into a WebM video while running
+    video: RwLock<Option<web_sys::MediaRecorder>>,
     mousedown: RwLock<bool>,
+    interaction_mode: RwLock<InteractionMode>,
+    /// Animation of the view back to the whole board, started with Home or a double click
+    view_tween: RwLock<Option<ViewTween>>,
+    /// Mirrors the pointer vertically before it is mapped to a cell
+    flip_y: RwLock<bool>,
+    kill_margin: u32,
+    /// Start time and lines of the input log while recording
+    recording: RwLock<Option<(f64, Vec<String>)>>,
+    /// Set once the WebGL context is lost, this state can not render anymore
+    context_lost: RwLock<bool>,
+    /// Jumping ahead with J, no frames are drawn until it is done
+    jumping: RwLock<bool>,
+    /// The live cells every `SNAPSHOT_INTERVAL_MS` and the generation they are from
+    snapshot: RwLock<Option<(u64, Pattern)>>,
+    /// Boards saved with Shift and a digit, by digit, with the generation they are from
+    slots: RwLock<HashMap<u32, (u64, Pattern)>>,
+    /// Clicks flip the cell under the cursor instead of painting
+    poke: RwLock<bool>,
+    /// Clicks draw straight lines instead of painting, from `anchor` to the cell of the second
+    /// click
+    line_mode: RwLock<bool>,
+    anchor: RwLock<Option<(u32, u32)>>,
+    /// Whether the current drag moves the view instead of drawing
+    panning: RwLock<bool>,
+    selecting: RwLock<bool>,
+    selection: RwLock<Option<Selection>>,
+    clipboard: RwLock<Option<(Vec<u8>, u32, u32)>>,
+    /// Cell under the cursor
     last_mousepos: RwLock<Option<(u32, u32)>>,
+    /// Canvas pixel under the cursor
+    last_screenpos: RwLock<Option<(u32, u32)>>,
+    prev_mousepos: RwLock<Option<(u32, u32)>>,
+    /// Whether the brush grows with the speed of the stroke, toggled with K
+    taper: RwLock<bool>,
+    /// Cell under the cursor at the previous frame, to measure the speed of the stroke
+    taper_mousepos: RwLock<Option<(u32, u32)>>,
+    brush_shape: RwLock<BrushShape>,
+    /// Fraction of the cells under the brush that the spray sets per frame, kept while the brush
+    /// is solid
+    spray_density: RwLock<f32>,
+    sim_kind: RwLock<SimKind>,
     start_mousepos: RwLock<Option<(u32, u32)>>,
     texture_size: wgpu::Extent3d,
-    texture: wgpu::Texture,
-    texture_target: wgpu::Texture,
-    texture_target_view: wgpu::TextureView,
-    texture_bind_group: wgpu::BindGroup,
+    cell_format: wgpu::TextureFormat,
+    layers: [Layer; 2],
+    dual_layer: RwLock<bool>,
     texture_target_bind_group: wgpu::BindGroup,
+    /// One row of `MAX_PALETTE_LEN` colors, only the first `palette.0.len()` are used
+    palette_texture: wgpu::Texture,
+    palette: RwLock<Palette>,
+    palette_preset: RwLock<PalettePreset>,
+    /// The first layer as it was before the latest generation while interpolating
+    previous_texture: wgpu::Texture,
+    /// What the cells were classified as by A, one byte per cell, see `CLASS_STILL_LIFE`
+    classes_texture: wgpu::Texture,
+    /// Slow motion: one generation every `interpolation_ms`, crossfaded from the previous one
+    interpolate: RwLock<bool>,
+    interpolation_ms: f64,
+    /// Generations advance at most this often, in batches of `steps_per_frame`, while drawing
+    /// and presenting still happen every frame. 0 advances every frame
+    step_interval_ms: RwLock<f64>,
+    /// Frames are drawn and presented at most this often, 0 draws every animation frame
+    draw_interval_ms: f64,
+    /// Frame timestamp of the latest generation
+    last_step_time: RwLock<f64>,
+    /// Timestamp of the latest frame that was drawn
+    last_draw_time: RwLock<f64>,
+    /// Time newborn cells take to grow to their full size once the birth animation is on
+    birth_animation_ms: f32,
     uniforms: RwLock<Uniforms>,
     uniforms_buffer: wgpu::Buffer,
+    /// The values 0 and 1, copied into the phase of `uniforms_buffer` before every checkerboard
+    /// step because all steps of a frame share the uniforms uploaded at its start
+    phase_buffer: wgpu::Buffer,
     uniforms_bind_group: wgpu::BindGroup,
 }
 
-#[derive(Debug)]
-enum CanvasEvent {
-    MouseMove(u32, u32),
-    MouseDown,
-    MouseUp,
+/// Outer-totalistic rule as bitmasks over the live neighbor count, e.g. bit 3 of `birth` is set
+/// when a dead cell with three live neighbors comes alive.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Rule {
+    birth: u32,
+    survive: u32,
+    _padding: [u32; 2],
 }
 
-impl State {
-    async fn new(canvas: &web_sys::HtmlCanvasElement) -> Self {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-        });
+// Only `u32` fields and no implicit padding, spelled out because the derives leave behind dead
+// code for it
+unsafe impl bytemuck::Zeroable for Rule {}
+unsafe impl bytemuck::Pod for Rule {}
 
-        let surface = unsafe { instance.create_surface_from_canvas(&canvas) }.unwrap();
+impl Rule {
+    const CONWAY: Rule = Rule::new(1 << 3, 1 << 2 | 1 << 3);
+    const HIGHLIFE: Rule = Rule::new(1 << 3 | 1 << 6, 1 << 2 | 1 << 3);
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
+    /// Rules that h cycles through, applied to the running board without clearing it.
+    const PRESETS: [(&'static str, Rule); 6] = [
+        ("Conway", Rule::CONWAY),
+        ("HighLife", Rule::HIGHLIFE),
+        ("Day & Night", Rule::new(0b111001000, 0b111011000)),
+        ("Seeds", Rule::new(1 << 2, 0)),
+        ("Life without Death", Rule::new(1 << 3, 0b111111111)),
+        ("Maze", Rule::new(1 << 3, 0b111110)),
+    ];
+
+    const fn new(birth: u32, survive: u32) -> Self {
+        Self {
+            birth,
+            survive,
+            _padding: [0; 2],
+        }
+    }
+}
+
+impl std::str::FromStr for Rule {
+    type Err = ();
+
+    /// Parses rules in B/S notation such as `B3/S23`.
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (birth, survive) = s.trim().split_once('/').ok_or(())?;
+        let mask = |digits: &str, prefix: char| -> Result<u32, ()> {
+            let digits = digits
+                .strip_prefix(prefix)
+                .or(digits.strip_prefix(prefix.to_ascii_lowercase()))
+                .ok_or(())?;
+            digits.chars().try_fold(0, |mask, c| match c.to_digit(10) {
+                Some(n) if n <= 8 => Ok(mask | 1 << n),
+                _ => Err(()),
             })
-            .await
-            .unwrap();
+        };
+        Ok(Rule::new(mask(birth, 'B')?, mask(survive, 'S')?))
+    }
+}
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
-                    limits: wgpu::Limits::downlevel_webgl2_defaults()
-                        .using_resolution(adapter.limits()),
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
+/// Inclusive range of live neighbor counts, written like `34..45`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct CountRange {
+    min: u32,
+    max: u32,
+}
 
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
+impl std::str::FromStr for CountRange {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (min, max) = s.trim().split_once("..").ok_or(())?;
+        let (min, max) = (min.parse().map_err(|_| ())?, max.parse().map_err(|_| ())?);
+        if min > max {
+            return Err(());
+        }
+        Ok(Self { min, max })
+    }
+}
+
+impl std::fmt::Display for CountRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.min, self.max)
+    }
+}
+
+/// Colors that cells are shown in by age, written as hex colors like `000000,ffffff,ff8000`.
+/// The first color is for dead cells, the others for cells that have been alive for one, two, ...
+/// generations, the last one for all older cells. Without any colors cells keep their own color.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Palette(Vec<[u8; 4]>);
+
+impl Palette {
+    /// The first color as a tint covering 60% of the cells below, for single color attributes.
+    fn tint(self) -> Option<[f32; 4]> {
+        let [r, g, b, _] = self.0.first()?.map(|c| c as f32 / 255.0);
+        Some([r, g, b, 0.6])
+    }
+}
+
+impl std::str::FromStr for Palette {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let colors = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|color| !color.is_empty())
+            .map(|color| {
+                let hex = color.strip_prefix('#').unwrap_or(color);
+                // `f80` is short for `ff8800`
+                let hex: String = match hex.len() {
+                    3 => hex.chars().flat_map(|c| [c, c]).collect(),
+                    6 => hex.to_owned(),
+                    _ => return Err(()),
+                };
+                let channel =
+                    |i: usize| u8::from_str_radix(hex.get(i..i + 2).ok_or(())?, 16).map_err(|_| ());
+                Ok([channel(0)?, channel(2)?, channel(4)?, 255])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if colors.len() > MAX_PALETTE_LEN as usize {
+            return Err(());
+        }
+        Ok(Self(colors))
+    }
+}
+
+/// Built-in palettes that stay readable with color vision deficiencies or low contrast
+/// sensitivity, each with gridline colors to match.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PalettePreset {
+    /// The cells' own colors and the default gridlines
+    Default,
+    /// White cells on black with yellow gridlines
+    HighContrast,
+    /// The Okabe-Ito colors, distinguishable with any common color vision deficiency
+    Colorblind,
+    /// A viridis ramp from yellow newborns to purple old cells, ordered by lightness
+    Viridis,
+}
+
+impl PalettePreset {
+    const ALL: [PalettePreset; 4] = [
+        Self::Default,
+        Self::HighContrast,
+        Self::Colorblind,
+        Self::Viridis,
+    ];
+
+    fn palette(self) -> Palette {
+        match self {
+            Self::Default => "",
+            Self::HighContrast => "000000,ffffff",
+            Self::Colorblind => "000000,e69f00,56b4e9,009e73,f0e442,0072b2,d55e00,cc79a7",
+            Self::Viridis => "000000,fde725,90d743,35b779,21918c,31688e,443983,440154",
+        }
+        .parse()
+        .unwrap()
+    }
+
+    /// Minor and major gridline colors
+    fn grid_colors(self) -> ([f32; 4], [f32; 4]) {
+        match self {
+            Self::HighContrast => ([1.0, 1.0, 0.0, 0.3], [1.0, 1.0, 0.0, 0.8]),
+            _ => ([1.0, 1.0, 1.0, 0.08], [1.0, 1.0, 1.0, 0.35]),
+        }
+    }
+}
+
+impl std::str::FromStr for PalettePreset {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "default" => Ok(Self::Default),
+            "high-contrast" => Ok(Self::HighContrast),
+            "colorblind" => Ok(Self::Colorblind),
+            "viridis" => Ok(Self::Viridis),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for PalettePreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Default => "default",
+            Self::HighContrast => "high-contrast",
+            Self::Colorblind => "colorblind",
+            Self::Viridis => "viridis",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::fmt::Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let colors = self
+            .0
             .iter()
-            .copied()
-            .filter(|f| f.describe().srgb)
-            .next()
-            .unwrap_or(surface_caps.formats[0]);
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            width: 1024,
-            height: 1024,
+            .map(|[r, g, b, _]| format!("{:02x}{:02x}{:02x}", r, g, b))
+            .collect::<Vec<_>>();
+        write!(f, "{}", colors.join(","))
+    }
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = |mask: u32| -> String {
+            (0..=8)
+                .filter(|n| mask & 1 << n != 0)
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
         };
+        write!(f, "B{}/S{}", digits(self.birth), digits(self.survive))
+    }
+}
 
-        surface.configure(&device, &config);
+/// A pattern stamped onto the board every `interval` generations, like the output of a gun that
+/// is not on the board.
+#[derive(Debug, Clone)]
+struct ScheduledStamp {
+    pattern: Pattern,
+    /// Top-left corner, the parts outside the board are dropped
+    x: i32,
+    y: i32,
+    interval: u64,
+    /// Generation the stamp was scheduled at, it is due every `interval` generations after it
+    start: u64,
+}
 
-        let texture_size = wgpu::Extent3d {
-            width: 1024,
-            height: 1024,
+/// Counts of the frames drawn since the settings last changed, a frame is dropped if it came
+/// more than `FRAME_BUDGET_MS` later than it was due.
+#[derive(Debug, Default, Copy, Clone)]
+struct FrameStats {
+    /// Timestamp of the first frame counted
+    since: Option<f64>,
+    frames: u64,
+    dropped: u64,
+    /// `dropped` as of the last time it was logged
+    logged_dropped: u64,
+}
+
+/// An animated change of the view, the zoom is interpolated geometrically so the speed looks even.
+#[derive(Debug, Copy, Clone)]
+struct ViewTween {
+    /// Timestamp of the first frame of the animation, set once that frame renders
+    start: Option<f64>,
+    from_zoom: f32,
+    from_center: [f32; 2],
+    to_zoom: f32,
+    to_center: [f32; 2],
+}
+
+/// Filterable, mipmapped copy of the first layer that is sampled instead of the cells while
+/// zoomed out, so distant boards are averaged instead of aliasing, and while zoomed in with
+/// smooth zoom on, so cells blend into their neighbors instead of ending in hard edges. Its
+/// smallest level is also what the glow blurs. The cells themselves are float textures that can
+/// not be filtered on WebGL.
+///
+/// The copy is 8-bit RGBA, 4 bytes per cell for the first level plus a third of that for the
+/// smaller ones, about 5.3 MiB on top of the layers for a 1024x1024 board. It is only refreshed
+/// on frames that sample it, writing the whole chain once or only the first level when zoomed in.
+struct DisplayMips {
+    /// All levels, sampled by the render pass
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    /// One view per level to render into
+    level_views: Vec<wgpu::TextureView>,
+    /// Bind group `i` samples level `i` while level `i + 1` is rendered
+    level_bind_groups: Vec<wgpu::BindGroup>,
+}
+
+impl DisplayMips {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+    fn new(
+        device: &wgpu::Device,
+        texture_size: wgpu::Extent3d,
+        downsample_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("display mips"),
+            size: texture_size,
+            mip_level_count: DISPLAY_MIP_LEVELS,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            view_formats: &[Self::FORMAT],
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let level_views: Vec<_> = (0..DISPLAY_MIP_LEVELS)
+            .map(|level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: std::num::NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let level_bind_groups = level_views[..level_views.len() - 1]
+            .iter()
+            .map(|level_view| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: downsample_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::TextureView(level_view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: wgpu::BindingResource::Sampler(&sampler),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        Self {
+            view,
+            sampler,
+            level_views,
+            level_bind_groups,
+        }
+    }
+}
+
+/// Live cells blurred into their surroundings and added onto the board as a glow. The blur runs
+/// on `GLOW_LEVEL` of the display copy, so it reads and writes a sixteenth of the cells, in two
+/// passes along each axis instead of one over the whole square around every texel.
+struct Glow {
+    /// Written by the horizontal pass, read by the vertical one
+    horizontal_view: wgpu::TextureView,
+    /// The finished glow, sampled by the render pass
+    view: wgpu::TextureView,
+    /// Sample `GLOW_LEVEL` of the display copy for the horizontal pass and `horizontal_view` for
+    /// the vertical one
+    horizontal_bind_group: wgpu::BindGroup,
+    vertical_bind_group: wgpu::BindGroup,
+}
+
+impl Glow {
+    fn new(
+        device: &wgpu::Device,
+        texture_size: wgpu::Extent3d,
+        downsample_bind_group_layout: &wgpu::BindGroupLayout,
+        display_mips: &DisplayMips,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: (texture_size.width >> GLOW_LEVEL).max(1),
+            height: (texture_size.height >> GLOW_LEVEL).max(1),
             depth_or_array_layers: 1,
         };
+        let [horizontal_view, view] = ["glow horizontal", "glow"].map(|label| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: DisplayMips::FORMAT,
+                    view_formats: &[DisplayMips::FORMAT],
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        let [horizontal_bind_group, vertical_bind_group] =
+            [&display_mips.level_views[GLOW_LEVEL], &horizontal_view].map(|source| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: downsample_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::TextureView(source),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: wgpu::BindingResource::Sampler(&display_mips.sampler),
+                        },
+                    ],
+                })
+            });
+
+        Self {
+            horizontal_view,
+            view,
+            horizontal_bind_group,
+            vertical_bind_group,
+        }
+    }
+}
 
+/// One independently simulated board: the texture the compute pass reads, the texture it
+/// renders the next generation into, and the rule it is advanced with.
+struct Layer {
+    texture: wgpu::Texture,
+    texture_view: wgpu::TextureView,
+    texture_target: wgpu::Texture,
+    texture_target_view: wgpu::TextureView,
+    texture_bind_group: wgpu::BindGroup,
+    rule: RwLock<Rule>,
+    rule_buffer: wgpu::Buffer,
+    rule_bind_group: wgpu::BindGroup,
+}
+
+impl Layer {
+    fn new(
+        device: &wgpu::Device,
+        texture_size: wgpu::Extent3d,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        rule_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        rule: Rule,
+    ) -> Self {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: None,
             size: texture_size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba32Float,
-            view_formats: &[wgpu::TextureFormat::Rgba32Float],
+            format,
+            view_formats: &[format],
             usage: wgpu::TextureUsages::TEXTURE_BINDING
                 | wgpu::TextureUsages::RENDER_ATTACHMENT
                 | wgpu::TextureUsages::COPY_DST,
@@ -143,429 +916,7361 @@ impl State {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba32Float,
-            view_formats: &[wgpu::TextureFormat::Rgba32Float],
+            format,
+            view_formats: &[format],
             usage: wgpu::TextureUsages::TEXTURE_BINDING
                 | wgpu::TextureUsages::RENDER_ATTACHMENT
-                | wgpu::TextureUsages::COPY_SRC,
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
         });
 
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                        multisampled: false,
-                    },
-                    count: None,
-                }],
-            });
-
-        let texture_target_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                        multisampled: false,
-                    },
-                    count: None,
-                }],
-            });
-
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let texture_target_view =
             texture_target.create_view(&wgpu::TextureViewDescriptor::default());
 
         let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
-            layout: &texture_bind_group_layout,
+            layout: texture_bind_group_layout,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
                 resource: wgpu::BindingResource::TextureView(&texture_view),
             }],
         });
 
-        let texture_target_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &texture_target_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&texture_target_view),
-            }],
-        });
-
-        let uniforms = Uniforms::new();
-        let uniforms_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let rule_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(&[uniforms]),
+            contents: bytemuck::cast_slice(&[rule]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
-
-        let uniforms_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: None,
-            });
-        let uniforms_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &uniforms_bind_group_layout,
+        let rule_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: rule_bind_group_layout,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
-                resource: uniforms_buffer.as_entire_binding(),
+                resource: rule_buffer.as_entire_binding(),
             }],
             label: None,
         });
 
-        // Create pipeline
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-        let compute_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&texture_bind_group_layout, &uniforms_bind_group_layout],
-                push_constant_ranges: &[],
+        Self {
+            texture,
+            texture_view,
+            texture_target,
+            texture_target_view,
+            texture_bind_group,
+            rule: RwLock::new(rule),
+            rule_buffer,
+            rule_bind_group,
+        }
+    }
+
+    /// Only rewrites the rule buffer, so the cells carry on evolving under the new rule from the
+    /// next generation on.
+    fn set_rule(&self, queue: &wgpu::Queue, rule: Rule) {
+        *self.rule.write().unwrap() = rule;
+        queue.write_buffer(&self.rule_buffer, 0, bytemuck::cast_slice(&[rule]));
+    }
+
+    /// Records one generation: the compute pass into `texture_target`, then the copy back into
+    /// `texture` so the next generation reads it. With a `region` only the cells inside it advance,
+    /// reading their neighbors from the unchanged cells around it.
+    fn encode_step(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        compute_pipeline: &wgpu::RenderPipeline,
+        uniforms_bind_group: &wgpu::BindGroup,
+        texture_size: wgpu::Extent3d,
+        region: Option<Selection>,
+    ) {
+        {
+            let mut compute_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("compute pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.texture_target_view,
+                    resolve_target: None,
+                    // The fullscreen triangle writes every texel, so clearing first is wasted work
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
             });
 
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[
-                    &texture_target_bind_group_layout,
-                    &uniforms_bind_group_layout,
-                ],
-                push_constant_ranges: &[],
-            });
+            compute_pass.set_pipeline(compute_pipeline);
+            compute_pass.set_bind_group(0, &self.texture_bind_group, &[]);
+            compute_pass.set_bind_group(1, uniforms_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.rule_bind_group, &[]);
+            if let Some((x0, y0, x1, y1)) = region {
+                compute_pass.set_scissor_rect(x0, y0, x1 - x0 + 1, y1 - y0 + 1);
+            }
+            compute_pass.draw(0..3, 0..1);
+        }
 
-        let compute_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Compute Pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_compute",
-                buffers: &[],
+        {
+            // Outside the region `texture_target` may be stale, e.g. after a stamp
+            let (origin, size) = match region {
+                Some((x0, y0, x1, y1)) => (
+                    wgpu::Origin3d { x: x0, y: y0, z: 0 },
+                    wgpu::Extent3d {
+                        width: x1 - x0 + 1,
+                        height: y1 - y0 + 1,
+                        depth_or_array_layers: 1,
+                    },
+                ),
+                None => (wgpu::Origin3d::default(), texture_size),
+            };
+            encoder.copy_texture_to_texture(
+                wgpu::ImageCopyTextureBase {
+                    texture: &self.texture_target,
+                    mip_level: 0,
+                    origin,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyTextureBase {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                size,
+            );
+        }
+    }
+}
+
+/// How the density of live cells varies across the board when randomizing. Densities are
+/// clamped to [0, 1].
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Gradient {
+    Uniform(f32),
+    /// From the left edge to the right edge
+    Horizontal {
+        from: f32,
+        to: f32,
+    },
+    /// From the top edge to the bottom edge
+    Vertical {
+        from: f32,
+        to: f32,
+    },
+    /// From the center to the corners
+    Radial {
+        center: f32,
+        edge: f32,
+    },
+}
+
+impl Gradient {
+    /// Density at (`u`, `v`), the position on the board scaled to [0, 1].
+    fn density(self, u: f32, v: f32) -> f32 {
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let density = match self {
+            Gradient::Uniform(density) => density,
+            Gradient::Horizontal { from, to } => lerp(from, to, u),
+            Gradient::Vertical { from, to } => lerp(from, to, v),
+            Gradient::Radial { center, edge } => {
+                let distance = ((u - 0.5).powi(2) + (v - 0.5).powi(2)).sqrt();
+                lerp(center, edge, distance / std::f32::consts::FRAC_1_SQRT_2)
+            }
+        };
+        density.clamp(0.0, 1.0)
+    }
+}
+
+impl std::str::FromStr for Gradient {
+    type Err = ();
+
+    /// Parses `0.3`, `horizontal:0.1:0.6`, `vertical:0.1:0.6` or `radial:0.6:0.05`.
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut parts = s.trim().split(':');
+        let kind = parts.next().ok_or(())?;
+        let mut number = || -> Result<f32, ()> {
+            let number: f32 = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+            number.is_finite().then_some(number).ok_or(())
+        };
+        let gradient = match kind {
+            "horizontal" => Gradient::Horizontal {
+                from: number()?,
+                to: number()?,
             },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_compute",
-                targets: &[Some(wgpu::TextureFormat::Rgba32Float.into())],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
+            "vertical" => Gradient::Vertical {
+                from: number()?,
+                to: number()?,
             },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+            "radial" => Gradient::Radial {
+                center: number()?,
+                edge: number()?,
             },
-            multiview: None,
-        });
+            density => Gradient::Uniform(density.parse().map_err(|_| ())?),
+        };
+        if parts.next().is_some() {
+            return Err(());
+        }
+        Ok(gradient)
+    }
+}
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+impl std::fmt::Display for Gradient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Gradient::Uniform(density) => write!(f, "{}", density),
+            Gradient::Horizontal { from, to } => write!(f, "horizontal:{}:{}", from, to),
+            Gradient::Vertical { from, to } => write!(f, "vertical:{}:{}", from, to),
+            Gradient::Radial { center, edge } => write!(f, "radial:{}:{}", center, edge),
+        }
+    }
+}
+
+/// SplitMix64, so that a soup can be reproduced from its seed alone.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+const SOUP_SIZE: u32 = 16;
+const SOUP_GENERATIONS: u32 = 1000;
+/// Wall-clock time the benchmark keeps the GPU busy with generations
+const BENCHMARK_MS: f64 = 3000.0;
+/// Generations submitted between checks of the clock while benchmarking
+const BENCHMARK_BATCH: u32 = 50;
+
+/// Neighbors past an edge of the board are read from the opposite edge
+const BOUNDARY_TORUS: u32 = 0;
+/// Neighbors past an edge of the board are dead
+const BOUNDARY_DEAD: u32 = 1;
+/// Neighbors past an edge of the board are mirrored back onto it
+const BOUNDARY_REFLECT: u32 = 2;
+
+/// All eight cells around a cell are its neighbors, as in classic Life
+const NEIGHBORHOOD_MOORE: u32 = 0;
+/// Only the four cells sharing an edge with a cell are its neighbors
+const NEIGHBORHOOD_VON_NEUMANN: u32 = 1;
+
+/// Cells per second a tapering stroke moves at to be drawn at the brush radius.
+const TAPER_SPEED: f64 = 300.0;
+/// Bounds of the brush radius while tapering, as multiples of the set radius
+const TAPER_MIN_SCALE: f32 = 0.4;
+const TAPER_MAX_SCALE: f32 = 2.0;
+/// Fraction of the way to the size for the current speed the brush goes every frame.
+const TAPER_EASING: f32 = 0.3;
+
+/// Bytes of the classes texture for cells of still lifes and oscillators, 0 for the rest.
+const CLASS_STILL_LIFE: u8 = 128;
+const CLASS_OSCILLATOR: u8 = 255;
+
+/// Frames per second captured into videos, at most as many as are drawn.
+const VIDEO_FRAME_RATE: f64 = 60.0;
+
+/// Longest period `period` looks for, every generation it looks further costs one CPU step of the
+/// live cells.
+const MAX_PERIOD: u32 = 30;
+
+/// Every cell advances every generation, classic Life
+const UPDATE_SYNCHRONOUS: u32 = 0;
+/// Only the cells with `x + y` as even or odd as the generation advance, the others keep their state
+const UPDATE_CHECKERBOARD: u32 = 1;
+
+/// The brush continues past an edge on the opposite one while the board wraps around
+const BRUSH_WRAP_AUTO: u32 = 0;
+const BRUSH_WRAP_ALWAYS: u32 = 1;
+const BRUSH_WRAP_NEVER: u32 = 2;
+
+/// Colors a palette can have, the palette texture is always this wide.
+const MAX_PALETTE_LEN: u32 = 256;
+
+/// Mixed with the generation to pick the cells that noise flips.
+const NOISE_SEED: u64 = 0x6e6f697365;
+
+/// How often a running board is read back to see whether any cell is still alive.
+const EXTINCTION_CHECK_INTERVAL_MS: f64 = 2000.0;
+
+/// How often the population is read back while the population graph is shown.
+const POPULATION_SAMPLE_INTERVAL_MS: f64 = 500.0;
+
+/// Samples the population graph keeps at most, however long its window is.
+const MAX_POPULATION_SAMPLES: usize = 1024;
+
+/// Size of the population graph in CSS pixels.
+const POPULATION_GRAPH_SIZE: (u32, u32) = (300, 100);
+
+/// Fastest speed the speed setting and Alt+wheel allow, in generations per frame.
+const MAX_SPEED: u32 = 1024;
+
+/// Widest quiet zone around loaded patterns.
+const MAX_QUIET_ZONE: u32 = 256;
+
+/// How long the speed or progress stays visible after it changed.
+const BADGE_MS: f64 = 1200.0;
+
+/// Keeps jumping ahead with J from running for minutes.
+const MAX_JUMP: u64 = 100_000;
+/// Generations submitted at once while jumping ahead, the page gets a turn between batches.
+const JUMP_BATCH: u64 = 500;
+
+/// Duration of the animation back to the whole board.
+const VIEW_TWEEN_MS: f64 = 250.0;
+
+/// Quiet time after the last resize event before the canvas is refitted.
+const RESIZE_DEBOUNCE_MS: i32 = 200;
+
+/// How often the board is copied back to restore it after a context loss. Each copy reads the
+/// whole board back from the GPU.
+const SNAPSHOT_INTERVAL_MS: f64 = 10_000.0;
+
+/// `localStorage` key of the input log recorded with r and replayed with R.
+const MACRO_STORAGE_KEY: &str = "life-macro";
+
+/// `localStorage` key prefix of the boards saved with Shift and a digit, followed by the digit.
+const SLOT_STORAGE_PREFIX: &str = "life-slot-";
+/// First line of saved slots, version 1 started right away with the generation.
+const SLOT_VERSION_TAG: &str = "v2";
+
+/// Version of the `settings()` string, in its `v` key. Strings without one are version 1.
+const SETTINGS_VERSION: u32 = 2;
+/// Upgrades a settings string of any version to the current one. Version 1 strings may lack
+/// any of the keys, those missing are taken from `defaults`, a complete current string, instead
+/// of keeping whatever is set now.
+fn migrate_settings(text: &str, defaults: &str) -> String {
+    fn key(pair: &str) -> &str {
+        pair.split_once('=').map_or(pair, |(key, _)| key)
+    }
+    let pairs: Vec<_> = text.split('&').filter(|pair| !pair.is_empty()).collect();
+    let version = pairs
+        .iter()
+        .find_map(|pair| pair.strip_prefix("v="))
+        .map_or(Some(1), |version| version.parse::<u32>().ok());
+    match version {
+        Some(1) => {}
+        Some(SETTINGS_VERSION) => return text.to_owned(),
+        Some(version) => {
+            warn!(
+                "Settings of version {} are newer than this build understands, applying what it can",
+                version
+            );
+            return text.to_owned();
+        }
+        None => {
+            warn!("Settings with an invalid version, applying them as the current version");
+            return text.to_owned();
+        }
+    }
+    // In the order of `defaults`, some keys like `palette` over `preset` depend on it
+    let default_pairs: Vec<_> = defaults
+        .split('&')
+        .filter(|pair| key(pair) != "v")
+        .collect();
+    let mut migrated: Vec<_> = default_pairs
+        .iter()
+        .map(|default| {
+            pairs
+                .iter()
+                .rfind(|pair| key(pair) == key(default))
+                .unwrap_or(default)
+        })
+        .collect();
+    // Left for `apply_settings` to warn about
+    migrated.extend(pairs.iter().filter(|pair| {
+        !default_pairs
+            .iter()
+            .any(|default| key(default) == key(pair))
+    }));
+    migrated.into_iter().copied().collect::<Vec<_>>().join("&")
+}
+
+/// Reads a slot saved by `save_slot`, of any version.
+fn parse_slot(stored: &str) -> Option<(u64, Pattern)> {
+    let stored = match stored.split_once('\n') {
+        Some((SLOT_VERSION_TAG, rest)) => rest,
+        // Version 1, without a tag
+        _ => stored,
+    };
+    let (generation, rle) = stored.split_once('\n')?;
+    Some((generation.parse().ok()?, Pattern::parse_rle(rle).ok()?))
+}
+
+/// Settings read from `data-*` attributes on the canvas element.
+struct Settings {
+    /// Start running right away, `data-autostart="false"` starts paused
+    autostart: bool,
+    /// Size of the board in cells, `data-board-width` and `data-board-height`
+    board_width: u32,
+    board_height: u32,
+    gif_frames: u32,
+    gif_step: u32,
+    video_seconds: f64,
+    /// Defaults to the whole board
+    region_width: Option<u32>,
+    region_height: Option<u32>,
+    smooth_outer_radius: f32,
+    /// Defaults to a third of the outer radius
+    smooth_inner_radius: Option<f32>,
+    smooth_sigmoid_width: f32,
+    second_rule: Rule,
+    turbo_steps: u32,
+    noise_flips: u32,
+    stamp_interval: u64,
+    /// Generations the population graph spans
+    population_window: u64,
+    warmup: u32,
+    /// Above 1 the rule switches from `Rule` bitmasks to the birth and survive ranges
+    neighbor_radius: u32,
+    birth_range: CountRange,
+    survive_range: CountRange,
+    /// Generations to show after the warmup before pausing on its own
+    max_generations: Option<u32>,
+    flip_y: bool,
+    /// Width of the border that absorbs escaping cells once it is switched on
+    kill_margin: u32,
+    palette: Palette,
+    /// Applied before `palette`, which replaces its colors if both are given
+    palette_preset: Option<PalettePreset>,
+    /// Body of the custom rule to start with, see `custom.wgsl`
+    rule_wgsl: Option<String>,
+    /// Random cells the board starts with instead of being empty, `data-initial-fill="0.3"` or
+    /// any gradient `randomize_with_gradient` takes, from `data-initial-seed` if given
+    initial_fill: Option<Gradient>,
+    initial_seed: Option<u64>,
+    /// Tints of the next generation preview, `data-dying-color="ff4d33"`
+    dying_color: Option<[f32; 4]>,
+    born_color: Option<[f32; 4]>,
+    /// Samples per pixel of the presentation pass, `data-msaa="4x"`
+    msaa: u32,
+    pause_on_extinction: bool,
+    pause_on_blur: bool,
+    draw_pauses: bool,
+    fit_pattern_margin: Option<f32>,
+    quiet_zone: u32,
+    /// Time each generation is crossfaded over in slow motion
+    interpolation_ms: f64,
+    /// Reach and strength of the glow, `data-glow-radius` in quarter-board texels
+    glow_radius: f32,
+    glow_intensity: f32,
+    step_interval_ms: f64,
+    draw_interval_ms: f64,
+    birth_animation_ms: f32,
+}
+
+impl Settings {
+    fn from_canvas(canvas: &web_sys::HtmlCanvasElement) -> Self {
+        Self {
+            autostart: attribute(canvas, "data-autostart").unwrap_or(true),
+            board_width: attribute(canvas, "data-board-width").unwrap_or(1024),
+            board_height: attribute(canvas, "data-board-height").unwrap_or(1024),
+            gif_frames: attribute(canvas, "data-gif-frames").unwrap_or(30),
+            gif_step: attribute(canvas, "data-gif-step").unwrap_or(1),
+            video_seconds: attribute(canvas, "data-video-seconds").unwrap_or(10.0),
+            region_width: attribute(canvas, "data-region-width"),
+            region_height: attribute(canvas, "data-region-height"),
+            smooth_outer_radius: attribute(canvas, "data-smooth-outer-radius").unwrap_or(7.0),
+            smooth_inner_radius: attribute(canvas, "data-smooth-inner-radius"),
+            smooth_sigmoid_width: attribute(canvas, "data-smooth-sigmoid-width").unwrap_or(0.028),
+            second_rule: attribute(canvas, "data-second-rule").unwrap_or(Rule::HIGHLIFE),
+            turbo_steps: attribute(canvas, "data-turbo-steps").unwrap_or(16).max(1),
+            noise_flips: attribute(canvas, "data-noise-flips").unwrap_or(8),
+            stamp_interval: attribute(canvas, "data-stamp-interval")
+                .unwrap_or(30)
+                .max(1),
+            population_window: attribute(canvas, "data-population-window")
+                .unwrap_or(1000)
+                .max(1),
+            warmup: attribute(canvas, "data-warmup").map_or(0, |warmup: u32| {
+                if warmup > MAX_WARMUP {
+                    warn!("Capping data-warmup={} to {}", warmup, MAX_WARMUP);
+                }
+                warmup.min(MAX_WARMUP)
             }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+            neighbor_radius: attribute(canvas, "data-neighbor-radius").unwrap_or(1),
+            birth_range: attribute(canvas, "data-birth-range")
+                .unwrap_or(CountRange { min: 3, max: 3 }),
+            survive_range: attribute(canvas, "data-survive-range")
+                .unwrap_or(CountRange { min: 2, max: 3 }),
+            max_generations: attribute(canvas, "data-max-generations"),
+            flip_y: attribute(canvas, "data-flip-y").unwrap_or(false),
+            kill_margin: attribute(canvas, "data-kill-margin").unwrap_or(4).max(1),
+            palette: attribute(canvas, "data-palette").unwrap_or_default(),
+            palette_preset: attribute(canvas, "data-palette-preset"),
+            rule_wgsl: canvas.get_attribute("data-rule-wgsl"),
+            initial_fill: attribute(canvas, "data-initial-fill"),
+            initial_seed: attribute(canvas, "data-initial-seed"),
+            dying_color: attribute::<Palette>(canvas, "data-dying-color").and_then(Palette::tint),
+            born_color: attribute::<Palette>(canvas, "data-born-color").and_then(Palette::tint),
+            pause_on_extinction: attribute(canvas, "data-pause-on-extinction").unwrap_or(false),
+            pause_on_blur: attribute(canvas, "data-pause-on-blur").unwrap_or(true),
+            draw_pauses: attribute(canvas, "data-draw-pauses").unwrap_or(false),
+            fit_pattern_margin: attribute(canvas, "data-fit-pattern")
+                .unwrap_or(false)
+                .then(|| {
+                    attribute::<f32>(canvas, "data-fit-margin")
+                        .unwrap_or(0.2)
+                        .clamp(0.0, 0.9)
+                }),
+            quiet_zone: attribute(canvas, "data-quiet-zone").unwrap_or(0),
+            interpolation_ms: attribute::<f64>(canvas, "data-interpolation-ms")
+                .unwrap_or(500.0)
+                .max(1.0),
+            glow_radius: attribute(canvas, "data-glow-radius").unwrap_or(4.0),
+            glow_intensity: attribute(canvas, "data-glow-intensity").unwrap_or(0.8),
+            step_interval_ms: attribute::<f64>(canvas, "data-step-interval-ms")
+                .unwrap_or(0.0)
+                .max(0.0),
+            draw_interval_ms: attribute::<f64>(canvas, "data-draw-interval-ms")
+                .unwrap_or(0.0)
+                .max(0.0),
+            birth_animation_ms: attribute::<f32>(canvas, "data-birth-animation-ms")
+                .unwrap_or(150.0)
+                .max(1.0),
+            msaa: canvas.get_attribute("data-msaa").map_or(1, |msaa| {
+                match msaa.trim_end_matches('x').parse() {
+                    Ok(samples @ (1 | 2 | 4)) => samples,
+                    _ => {
+                        warn!("Ignoring invalid data-msaa={:?}", msaa);
+                        1
+                    }
+                }
+            }),
+        }
+    }
+}
+
+fn attribute<T: std::str::FromStr>(canvas: &web_sys::HtmlCanvasElement, name: &str) -> Option<T> {
+    let value = canvas.get_attribute(name)?;
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            warn!("Ignoring invalid {}={:?}", name, value);
+            None
+        }
+    }
+}
+
+/// The multisampled color target of the presentation pass for the surface in `config`, or none
+/// for a single sample.
+fn create_msaa_view(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    samples: u32,
+) -> Option<wgpu::TextureView> {
+    if samples == 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("MSAA"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: samples,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Resolves after `ms` milliseconds without blocking the page.
+async fn sleep(ms: f64) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms.max(0.0) as i32)
+            .unwrap();
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Offers `bytes` to the user as a file download.
+fn download(bytes: &[u8], mime: &str, filename: &str) {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+        &parts,
+        web_sys::BlobPropertyBag::new().type_(mime),
+    )
+    .unwrap();
+    download_blob(&blob, filename);
+}
+
+/// Offers `blob` to the user as a file download.
+fn download_blob(blob: &web_sys::Blob, filename: &str) {
+    let url = web_sys::Url::create_object_url_with_blob(blob).unwrap();
+
+    let doc = web_sys::window().unwrap().document().unwrap();
+    let anchor = doc
+        .create_element("a")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .unwrap();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).unwrap();
+}
+
+#[derive(Debug)]
+enum CanvasEvent {
+    /// Canvas pixel under the cursor
+    MouseMove(u32, u32),
+    /// Zoom in for negative, out for positive deltas
+    Wheel(f64),
+    /// Wheel with Alt held, faster for negative, slower for positive deltas
+    SpeedWheel(f64),
+    MouseDown {
+        shift: bool,
+        alt: bool,
+    },
+    MouseUp,
+    MouseLeave,
+    Play,
+    Pause,
+    Step,
+    Clear,
+    /// Fill the board with random cells whose density follows the gradient, from the given seed
+    /// or a fresh one
+    Randomize(Gradient, Option<u64>),
+    /// Flip this many random cells of the running board
+    InjectNoise(u32),
+    /// The page became visible (true) or hidden (false)
+    Visibility(bool),
+    /// The window gained (true) or lost (false) focus
+    Focus(bool),
+    /// The viewport settled on a new size or orientation
+    Resize,
+    /// Zoom out to the whole board
+    ResetView,
+    /// A pattern to stamp centered on the cell under the given canvas pixel, or on the board
+    /// center
+    LoadPattern(Pattern, Option<(u32, u32)>),
+    KeyDown {
+        key: String,
+        ctrl: bool,
+    },
+    /// The WebGL context was lost, everything on the device is gone
+    ContextLost,
+    /// The browser allows creating a new WebGL context after a loss
+    ContextRestored,
+    /// Rebuild the board with another size
+    Reconfigure(GridConfig),
+    /// Save the board under the slot of this digit
+    SaveSlot(u32),
+    /// Replace the board with the one saved under the slot of this digit
+    RestoreSlot(u32),
+}
+
+/// What a `Binding` does, `State::input` matches on it so every key is listed in `BINDINGS` only.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Action {
+    Help,
+    Pause,
+    Step,
+    Turbo,
+    Jump,
+    NextRule,
+    SimKind,
+    Layers,
+    Neighborhood,
+    UpdateScheme,
+    Boundary,
+    KillMargin,
+    BrushShape,
+    BrushSize,
+    Spray,
+    SprayDensity,
+    Taper,
+    BrushWrap,
+    Snap,
+    Poke,
+    LineMode,
+    InteractionMode,
+    Noise,
+    Stamp,
+    ClearStamps,
+    StepSelection,
+    ClearSelection,
+    Copy,
+    Paste,
+    Transform,
+    MoveCursor,
+    ToggleCell,
+    Cancel,
+    ResetView,
+    ScrollPastEdges,
+    Fullscreen,
+    CellStyle,
+    Brick,
+    Grid,
+    Minimap,
+    Counter,
+    Channel,
+    Smoothing,
+    Blend,
+    Glow,
+    Palette,
+    ShowChanges,
+    Preview,
+    SlowMotion,
+    GrowIn,
+    Classify,
+    Inspect,
+    Stats,
+    PopulationGraph,
+    Benchmark,
+    SoupSearch,
+    WriteSettings,
+    Export,
+    ExportGif,
+    Video,
+    Record,
+    Replay,
+    SaveSlot,
+    RestoreSlot,
+}
+
+/// A keyboard shortcut, as listed by the help `?` toggles.
+struct Binding {
+    /// `KeyboardEvent.key` values it reacts to, only keys listed here reach `State::input`
+    keys: &'static [&'static str],
+    /// Whether Ctrl or Cmd has to be held down, `None` for either way
+    ctrl: Option<bool>,
+    action: Action,
+    /// How the help writes the keys
+    label: &'static str,
+    description: &'static str,
+    /// What the setting it switches is at right now
+    state: Option<fn(&State) -> String>,
+}
+
+impl Binding {
+    fn matches(&self, key: &str, ctrl: bool) -> bool {
+        self.keys.contains(&key) && self.ctrl.is_none_or(|c| c == ctrl)
+    }
+}
+
+fn on_off(on: bool) -> String {
+    if on { "on" } else { "off" }.to_owned()
+}
+
+/// Every keyboard shortcut, `setup_listeners` drops keys that are not in here.
+const BINDINGS: &[Binding] = &[
+    Binding {
+        keys: &["?"],
+        ctrl: Some(false),
+        action: Action::Help,
+        label: "?",
+        description: "Show or hide this help",
+        state: None,
+    },
+    Binding {
+        keys: &[" "],
+        ctrl: Some(false),
+        action: Action::Pause,
+        label: "Space",
+        description: "Pause or resume, toggles the cell at the edit cursor while there is one",
+        state: Some(|state| {
+            if *state.paused.read().unwrap() {
+                "paused"
+            } else {
+                "running"
+            }
+            .to_owned()
+        }),
+    },
+    Binding {
+        keys: &["."],
+        ctrl: Some(false),
+        action: Action::Step,
+        label: ".",
+        description: "Advance one generation",
+        state: None,
+    },
+    Binding {
+        keys: &["t"],
+        ctrl: Some(false),
+        action: Action::Turbo,
+        label: "t",
+        description: "Switch between one and many generations per frame",
+        state: Some(|state| {
+            format!(
+                "{} per frame",
+                *state.target_steps_per_frame.read().unwrap()
+            )
+        }),
+    },
+    Binding {
+        keys: &["J"],
+        ctrl: Some(false),
+        action: Action::Jump,
+        label: "J",
+        description: "Jump ahead a number of generations",
+        state: None,
+    },
+    Binding {
+        keys: &["h"],
+        ctrl: Some(false),
+        action: Action::NextRule,
+        label: "h",
+        description: "Next preset rule",
+        state: Some(|state| state.layers[0].rule.read().unwrap().to_string()),
+    },
+    Binding {
+        keys: &["m"],
+        ctrl: Some(false),
+        action: Action::SimKind,
+        label: "m",
+        description: "Switch between classic Life and SmoothLife",
+        state: Some(|state| format!("{:?}", *state.sim_kind.read().unwrap())),
+    },
+    Binding {
+        keys: &["l"],
+        ctrl: Some(false),
+        action: Action::Layers,
+        label: "l",
+        description: "Simulate one or two layers",
+        state: Some(|state| state.uniforms.read().unwrap().layers.to_string()),
+    },
+    Binding {
+        keys: &["V"],
+        ctrl: Some(false),
+        action: Action::Neighborhood,
+        label: "V",
+        description: "Switch between the Moore and von Neumann neighborhoods",
+        state: Some(|state| {
+            match state.uniforms.read().unwrap().neighborhood {
+                NEIGHBORHOOD_MOORE => "Moore",
+                _ => "von Neumann",
+            }
+            .to_owned()
+        }),
+    },
+    Binding {
+        keys: &["B"],
+        ctrl: Some(false),
+        action: Action::UpdateScheme,
+        label: "B",
+        description: "Switch between synchronous and checkerboard updates",
+        state: Some(|state| {
+            match state.uniforms.read().unwrap().update_scheme {
+                UPDATE_SYNCHRONOUS => "synchronous",
+                _ => "checkerboard",
+            }
+            .to_owned()
+        }),
+    },
+    Binding {
+        keys: &["o"],
+        ctrl: Some(false),
+        action: Action::Boundary,
+        label: "o",
+        description: "Cycle the edges between wrapping, dead and mirrored",
+        state: Some(|state| {
+            match state.uniforms.read().unwrap().boundary_mode {
+                BOUNDARY_TORUS => "wrapping",
+                BOUNDARY_DEAD => "dead",
+                _ => "mirrored",
+            }
+            .to_owned()
+        }),
+    },
+    Binding {
+        keys: &["w"],
+        ctrl: Some(false),
+        action: Action::KillMargin,
+        label: "w",
+        description: "Kill the cells near the edges",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().kill_margin > 0)),
+    },
+    Binding {
+        keys: &["b"],
+        ctrl: Some(false),
+        action: Action::BrushShape,
+        label: "b",
+        description: "Next brush shape",
+        state: Some(|state| format!("{:?}", *state.brush_shape.read().unwrap())),
+    },
+    Binding {
+        keys: &["[", "]"],
+        ctrl: Some(false),
+        action: Action::BrushSize,
+        label: "[ ]",
+        description: "Shrink or grow the brush",
+        state: Some(|state| format!("radius {}", state.uniforms.read().unwrap().brush_radius)),
+    },
+    Binding {
+        keys: &["y"],
+        ctrl: Some(false),
+        action: Action::Spray,
+        label: "y",
+        description: "Switch between a solid brush and a spray",
+        state: Some(|state| {
+            let density = state.uniforms.read().unwrap().brush_density;
+            if density < 1.0 {
+                format!("spraying {:.0}%", density * 100.0)
+            } else {
+                "solid".to_owned()
+            }
+        }),
+    },
+    Binding {
+        keys: &["{", "}"],
+        ctrl: Some(false),
+        action: Action::SprayDensity,
+        label: "{ }",
+        description: "Spray fewer or more cells",
+        state: Some(|state| format!("{:.0}%", *state.spray_density.read().unwrap() * 100.0)),
+    },
+    Binding {
+        keys: &["K"],
+        ctrl: Some(false),
+        action: Action::Taper,
+        label: "K",
+        description: "Taper the brush with the speed of the stroke",
+        state: Some(|state| on_off(*state.taper.read().unwrap())),
+    },
+    Binding {
+        keys: &["W"],
+        ctrl: Some(false),
+        action: Action::BrushWrap,
+        label: "W",
+        description: "Cycle whether the brush wraps around the edges",
+        state: Some(|state| {
+            match state.uniforms.read().unwrap().brush_wrap {
+                BRUSH_WRAP_AUTO => "with the board",
+                BRUSH_WRAP_ALWAYS => "always",
+                _ => "never",
+            }
+            .to_owned()
+        }),
+    },
+    Binding {
+        keys: &["S"],
+        ctrl: Some(false),
+        action: Action::Snap,
+        label: "S",
+        description: "Snap the brush to cell centers",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().snap == 1)),
+    },
+    Binding {
+        keys: &["x"],
+        ctrl: Some(false),
+        action: Action::Poke,
+        label: "x",
+        description: "Toggle single cells instead of painting",
+        state: Some(|state| on_off(*state.poke.read().unwrap())),
+    },
+    Binding {
+        keys: &["L"],
+        ctrl: Some(false),
+        action: Action::LineMode,
+        label: "L",
+        description: "Draw straight lines with two clicks",
+        state: Some(|state| on_off(*state.line_mode.read().unwrap())),
+    },
+    Binding {
+        keys: &["p"],
+        ctrl: Some(false),
+        action: Action::InteractionMode,
+        label: "p",
+        description: "Switch dragging between drawing and panning",
+        state: Some(|state| format!("{:?}", *state.interaction_mode.read().unwrap())),
+    },
+    Binding {
+        keys: &["n"],
+        ctrl: Some(false),
+        action: Action::Noise,
+        label: "n",
+        description: "Flip random cells",
+        state: None,
+    },
+    Binding {
+        keys: &["q"],
+        ctrl: Some(false),
+        action: Action::Stamp,
+        label: "q",
+        description: "Stamp the last loaded pattern at the cursor repeatedly",
+        state: None,
+    },
+    Binding {
+        keys: &["Q"],
+        ctrl: Some(false),
+        action: Action::ClearStamps,
+        label: "Q",
+        description: "Clear the scheduled stamps",
+        state: None,
+    },
+    Binding {
+        keys: &["e"],
+        ctrl: Some(false),
+        action: Action::StepSelection,
+        label: "e",
+        description: "Advance the selection one generation",
+        state: None,
+    },
+    Binding {
+        keys: &["Delete", "Backspace"],
+        ctrl: None,
+        action: Action::ClearSelection,
+        label: "Delete",
+        description: "Clear the selection",
+        state: None,
+    },
+    Binding {
+        keys: &["c"],
+        ctrl: Some(true),
+        action: Action::Copy,
+        label: "Ctrl+C",
+        description: "Copy the selection",
+        state: None,
+    },
+    Binding {
+        keys: &["v"],
+        ctrl: Some(true),
+        action: Action::Paste,
+        label: "Ctrl+V",
+        description: "Paste at the cursor",
+        state: None,
+    },
+    Binding {
+        keys: &["T"],
+        ctrl: Some(false),
+        action: Action::Transform,
+        label: "T",
+        description: "Turn the copied region by 90°",
+        state: None,
+    },
+    Binding {
+        keys: &["X", "Y"],
+        ctrl: Some(false),
+        action: Action::Transform,
+        label: "X Y",
+        description: "Mirror the copied region horizontally or vertically",
+        state: None,
+    },
+    Binding {
+        keys: &["ArrowLeft", "ArrowRight", "ArrowUp", "ArrowDown"],
+        ctrl: Some(false),
+        action: Action::MoveCursor,
+        label: "Arrows",
+        description: "Move the edit cursor, Enter toggles the cell under it",
+        state: None,
+    },
+    Binding {
+        keys: &["Enter"],
+        ctrl: Some(false),
+        action: Action::ToggleCell,
+        label: "Enter",
+        description: "Toggle the cell at the edit cursor",
+        state: None,
+    },
+    Binding {
+        keys: &["Escape"],
+        ctrl: None,
+        action: Action::Cancel,
+        label: "Escape",
+        description: "Cancel the selection, edit cursor, line and paste outline",
+        state: None,
+    },
+    Binding {
+        keys: &["Home"],
+        ctrl: None,
+        action: Action::ResetView,
+        label: "Home",
+        description: "Zoom out to the whole board",
+        state: None,
+    },
+    Binding {
+        keys: &["U"],
+        ctrl: Some(false),
+        action: Action::ScrollPastEdges,
+        label: "U",
+        description: "Scroll past the edges of a wrapping board",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().scrolling == 1)),
+    },
+    Binding {
+        keys: &["f"],
+        ctrl: Some(false),
+        action: Action::Fullscreen,
+        label: "f",
+        description: "Fullscreen",
+        state: None,
+    },
+    Binding {
+        keys: &["c"],
+        ctrl: Some(false),
+        action: Action::CellStyle,
+        label: "c",
+        description: "Draw cells as squares or circles",
+        state: Some(|state| {
+            if state.uniforms.read().unwrap().cell_style == 1 {
+                "circles"
+            } else {
+                "squares"
+            }
+            .to_owned()
+        }),
+    },
+    Binding {
+        keys: &["Z"],
+        ctrl: Some(false),
+        action: Action::Brick,
+        label: "Z",
+        description: "Shift every other row by half a cell",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().brick == 1)),
+    },
+    Binding {
+        keys: &["G"],
+        ctrl: Some(false),
+        action: Action::Grid,
+        label: "G",
+        description: "Cycle the major gridlines",
+        state: Some(
+            |state| match state.uniforms.read().unwrap().major_grid_interval {
+                0 => "off".to_owned(),
+                n => format!("every {}", n),
             },
-            multiview: None,
+        ),
+    },
+    Binding {
+        keys: &["M"],
+        ctrl: Some(false),
+        action: Action::Minimap,
+        label: "M",
+        description: "Show the minimap",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().minimap == 1)),
+    },
+    Binding {
+        keys: &["#"],
+        ctrl: Some(false),
+        action: Action::Counter,
+        label: "#",
+        description: "Move the generation counter to the next corner",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().counter_corner > 0)),
+    },
+    Binding {
+        keys: &["v"],
+        ctrl: Some(false),
+        action: Action::Channel,
+        label: "v",
+        description: "Show the next color channel of the board",
+        state: Some(|state| {
+            match state.uniforms.read().unwrap().view_channel {
+                0 => "all",
+                n => ["R", "G", "B", "A"][n as usize - 1],
+            }
+            .to_owned()
+        }),
+    },
+    Binding {
+        keys: &["a"],
+        ctrl: Some(false),
+        action: Action::Smoothing,
+        label: "a",
+        description: "Smooth the board when zoomed out",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().filtered_zoom == 1)),
+    },
+    Binding {
+        keys: &["z"],
+        ctrl: Some(false),
+        action: Action::Blend,
+        label: "z",
+        description: "Blend cells into each other when zoomed in",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().smooth_zoom == 1)),
+    },
+    Binding {
+        keys: &["O"],
+        ctrl: Some(false),
+        action: Action::Glow,
+        label: "O",
+        description: "Make live cells glow",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().glow == 1)),
+    },
+    Binding {
+        keys: &["C"],
+        ctrl: Some(false),
+        action: Action::Palette,
+        label: "C",
+        description: "Next palette",
+        state: Some(|state| state.palette_preset.read().unwrap().to_string()),
+    },
+    Binding {
+        keys: &["d"],
+        ctrl: Some(false),
+        action: Action::ShowChanges,
+        label: "d",
+        description: "Highlight the cells that changed",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().show_changes == 1)),
+    },
+    Binding {
+        keys: &["N"],
+        ctrl: Some(false),
+        action: Action::Preview,
+        label: "N",
+        description: "Preview the next generation",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().predict == 1)),
+    },
+    Binding {
+        keys: &["I"],
+        ctrl: Some(false),
+        action: Action::SlowMotion,
+        label: "I",
+        description: "Slow motion",
+        state: Some(|state| on_off(*state.interpolate.read().unwrap())),
+    },
+    Binding {
+        keys: &["P"],
+        ctrl: Some(false),
+        action: Action::GrowIn,
+        label: "P",
+        description: "Let newborn cells grow in",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().birth_animation_ms > 0.0)),
+    },
+    Binding {
+        keys: &["A"],
+        ctrl: Some(false),
+        action: Action::Classify,
+        label: "A",
+        description: "Tint still lifes and oscillators",
+        state: Some(|state| on_off(state.uniforms.read().unwrap().classified == 1)),
+    },
+    Binding {
+        keys: &["i"],
+        ctrl: Some(false),
+        action: Action::Inspect,
+        label: "i",
+        description: "Inspect the cell under the cursor",
+        state: Some(|state| on_off(*state.inspect.read().unwrap())),
+    },
+    Binding {
+        keys: &["F"],
+        ctrl: Some(false),
+        action: Action::Stats,
+        label: "F",
+        description: "Show the frame rate",
+        state: Some(|state| on_off(!state.frame_stats_output.hidden())),
+    },
+    Binding {
+        keys: &["H"],
+        ctrl: Some(false),
+        action: Action::PopulationGraph,
+        label: "H",
+        description: "Show the population graph",
+        state: Some(|state| on_off(!state.population_graph.hidden())),
+    },
+    Binding {
+        keys: &["k"],
+        ctrl: Some(false),
+        action: Action::Benchmark,
+        label: "k",
+        description: "Measure how many generations per second run",
+        state: None,
+    },
+    Binding {
+        keys: &["s"],
+        ctrl: Some(false),
+        action: Action::SoupSearch,
+        label: "s",
+        description: "Search random soups for the largest final population",
+        state: Some(|state| on_off(*state.searching.read().unwrap())),
+    },
+    Binding {
+        keys: &["u"],
+        ctrl: Some(false),
+        action: Action::WriteSettings,
+        label: "u",
+        description: "Write the settings into the address",
+        state: None,
+    },
+    Binding {
+        keys: &["E"],
+        ctrl: Some(false),
+        action: Action::Export,
+        label: "E",
+        description: "Export the board as a pattern file",
+        state: None,
+    },
+    Binding {
+        keys: &["g"],
+        ctrl: Some(false),
+        action: Action::ExportGif,
+        label: "g",
+        description: "Export a GIF",
+        state: None,
+    },
+    Binding {
+        keys: &["D"],
+        ctrl: Some(false),
+        action: Action::Video,
+        label: "D",
+        description: "Record a video",
+        state: Some(|state| on_off(state.video.read().unwrap().is_some())),
+    },
+    Binding {
+        keys: &["r"],
+        ctrl: Some(false),
+        action: Action::Record,
+        label: "r",
+        description: "Record input",
+        state: Some(|state| on_off(state.recording.read().unwrap().is_some())),
+    },
+    Binding {
+        keys: &["R"],
+        ctrl: Some(false),
+        action: Action::Replay,
+        label: "R",
+        description: "Replay the recorded input",
+        state: None,
+    },
+    // The digits are read from the physical key in `setup_listeners`, before any of these
+    Binding {
+        keys: &[],
+        ctrl: None,
+        action: Action::SaveSlot,
+        label: "Shift+1…9",
+        description: "Save the board to a slot",
+        state: None,
+    },
+    Binding {
+        keys: &[],
+        ctrl: None,
+        action: Action::RestoreSlot,
+        label: "1…9",
+        description: "Restore the board from a slot",
+        state: None,
+    },
+];
+
+/// Size of the board and what happens to its cells when it changes, see `State::reconfigured`.
+#[derive(Debug, Copy, Clone)]
+struct GridConfig {
+    width: u32,
+    height: u32,
+    /// Stretch the board onto the new size instead of keeping the size of its cells and
+    /// centering it
+    rescale: bool,
+}
+
+#[derive(Debug)]
+enum InitError {
+    SurfaceCreation(wgpu::CreateSurfaceError),
+    NoAdapter,
+    DeviceRequest(wgpu::RequestDeviceError),
+    NoSurfaceFormat,
+    NoCellFormat,
+    /// The shader modules do not fit together or do not compile
+    Shader(String),
+    /// No element has the id the simulation was started on
+    MissingCanvas(String),
+    /// The element with the id is not a canvas, named by its tag
+    NotACanvas(String, String),
+    /// The board to carry over into a rebuilt state could not be read back
+    Readback(String),
+}
+
+impl std::fmt::Display for InitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InitError::SurfaceCreation(e) => {
+                write!(f, "Could not create a surface on the canvas: {}", e)
+            }
+            InitError::NoAdapter => write!(
+                f,
+                "No graphics adapter is available. Your browser needs WebGPU or WebGL2 support."
+            ),
+            InitError::DeviceRequest(e) => write!(f, "Could not open the graphics device: {}", e),
+            InitError::NoCellFormat => write!(
+                f,
+                "The graphics device cannot render into any of {:?}",
+                CELL_FORMATS
+            ),
+            InitError::NoSurfaceFormat => {
+                write!(f, "The canvas does not support any texture format")
+            }
+            InitError::Shader(e) => write!(f, "Invalid shader: {}", e),
+            InitError::MissingCanvas(id) => write!(
+                f,
+                "There is no element with id {:?}, expected a <canvas id={:?}>",
+                id, id
+            ),
+            InitError::NotACanvas(id, tag) => write!(
+                f,
+                "The element with id {:?} is a <{}>, expected a <canvas>",
+                id,
+                tag.to_ascii_lowercase()
+            ),
+            InitError::Readback(e) => write!(f, "Could not carry the board over: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InitError {}
+
+/// The pipeline that advances a layer by one generation with `fs_compute` of `shader`.
+fn create_compute_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    cell_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Compute Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_compute",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_compute",
+            targets: &[Some(cell_format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+impl State {
+    async fn new(
+        canvas: &web_sys::HtmlCanvasElement,
+        settings: Settings,
+    ) -> Result<Self, InitError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: Default::default(),
         });
 
-        Self {
-            surface,
-            device,
-            queue,
-            config,
-            compute_pipeline,
-            render_pipeline,
-            mousedown: RwLock::new(false),
-            last_mousepos: RwLock::new(None),
-            start_mousepos: RwLock::new(None),
-            texture_size,
-            texture,
-            texture_target,
-            texture_target_view,
-            texture_bind_group,
-            texture_target_bind_group,
-            uniforms: RwLock::new(uniforms),
-            uniforms_buffer,
-            uniforms_bind_group,
+        let surface = unsafe { instance.create_surface_from_canvas(&canvas) }
+            .map_err(InitError::SurfaceCreation)?;
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or(InitError::NoAdapter)?;
+        let adapter_info = describe_adapter(&adapter.get_info());
+        warn!("Running on {}", adapter_info);
+        ADAPTER_INFO.with(|cache| *cache.borrow_mut() = Some(adapter_info));
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                    limits: wgpu::Limits::downlevel_webgl2_defaults()
+                        .using_resolution(adapter.limits()),
+                    label: None,
+                },
+                None,
+            )
+            .await
+            .map_err(InitError::DeviceRequest)?;
+        device.on_uncaptured_error(Box::new(|e| error!("Graphics device error: {}", e)));
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .copied()
+            .filter(|f| f.describe().srgb)
+            .next()
+            .or(surface_caps.formats.first().copied())
+            .ok_or(InitError::NoSurfaceFormat)?;
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            // Presenting once per display refresh paces frames evenly, which also keeps videos
+            // captured from the canvas smooth
+            present_mode: surface_caps
+                .present_modes
+                .iter()
+                .copied()
+                .find(|&mode| mode == wgpu::PresentMode::Fifo)
+                .unwrap_or(surface_caps.present_modes[0]),
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            width: 1024,
+            height: 1024,
+        };
+
+        surface.configure(&device, &config);
+
+        let msaa_samples = match settings.msaa {
+            1 => 1,
+            samples
+                if adapter
+                    .get_texture_format_features(config.format)
+                    .flags
+                    .sample_count_supported(samples) =>
+            {
+                samples
+            }
+            samples => {
+                warn!("{}x MSAA is not supported, presenting without it", samples);
+                1
+            }
+        };
+        let msaa_view = create_msaa_view(&device, &config, msaa_samples);
+
+        // Not every WebGL2 context can render into 32 bit float textures
+        let cell_format = CELL_FORMATS
+            .into_iter()
+            .find(|format| {
+                adapter
+                    .get_texture_format_features(*format)
+                    .allowed_usages
+                    .contains(
+                        wgpu::TextureUsages::TEXTURE_BINDING
+                            | wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::COPY_SRC
+                            | wgpu::TextureUsages::COPY_DST,
+                    )
+            })
+            .ok_or(InitError::NoCellFormat)?;
+        warn!("Storing cells as {:?}", cell_format);
+
+        let max = device.limits().max_texture_dimension_2d;
+        if settings.board_width.max(settings.board_height) > max {
+            warn!("Boards can be at most {0}x{0} cells on this device", max);
+        }
+        let texture_size = wgpu::Extent3d {
+            width: settings.board_width.clamp(1, max),
+            height: settings.board_height.clamp(1, max),
+            depth_or_array_layers: 1,
+        };
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+
+        let texture_target_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 7,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let downsample_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let rule_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: None,
+            });
+
+        let layers = [Rule::CONWAY, settings.second_rule].map(|rule| {
+            Layer::new(
+                &device,
+                texture_size,
+                &texture_bind_group_layout,
+                &rule_bind_group_layout,
+                cell_format,
+                rule,
+            )
+        });
+
+        let display_mips = DisplayMips::new(&device, texture_size, &downsample_bind_group_layout);
+        let glow = Glow::new(
+            &device,
+            texture_size,
+            &downsample_bind_group_layout,
+            &display_mips,
+        );
+        // Widest glow `data-glow-radius` can ask for on this backend
+        let max_glow_radius = if adapter.get_info().backend == wgpu::Backend::Gl {
+            MAX_GLOW_RADIUS_WEBGL
+        } else {
+            MAX_GLOW_RADIUS
+        };
+
+        // 1D textures are not available on WebGL, so the palette is a single row
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Palette"),
+            size: wgpu::Extent3d {
+                width: MAX_PALETTE_LEN,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let palette_view = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let previous_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Previous generation"),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: cell_format,
+            // Rendered to only by `clear_board`
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let previous_view = previous_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let classes_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Classes"),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let classes_view = classes_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let texture_target_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &texture_target_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&layers[0].texture_target_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&layers[1].texture_target_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&display_mips.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&display_mips.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&palette_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&previous_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&glow.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::TextureView(&classes_view),
+                },
+            ],
+        });
+
+        let mut uniforms = Uniforms::new();
+        uniforms.board_size = [texture_size.width as f32, texture_size.height as f32];
+        uniforms.active_region = {
+            let region_width = settings.region_width.map_or(texture_size.width, |width| {
+                width.clamp(1, texture_size.width)
+            });
+            let region_height = settings
+                .region_height
+                .map_or(texture_size.height, |height| {
+                    height.clamp(1, texture_size.height)
+                });
+            let x0 = (texture_size.width - region_width) / 2;
+            let y0 = (texture_size.height - region_height) / 2;
+            [
+                x0 as f32,
+                y0 as f32,
+                (x0 + region_width - 1) as f32,
+                (y0 + region_height - 1) as f32,
+            ]
+        };
+        uniforms.dying_color = settings.dying_color.unwrap_or(uniforms.dying_color);
+        uniforms.born_color = settings.born_color.unwrap_or(uniforms.born_color);
+        uniforms.outer_radius = settings.smooth_outer_radius.clamp(1.0, MAX_SMOOTH_RADIUS);
+        uniforms.inner_radius = settings
+            .smooth_inner_radius
+            .unwrap_or(uniforms.outer_radius / 3.0)
+            .clamp(0.5, uniforms.outer_radius);
+        uniforms.sigmoid_width = settings.smooth_sigmoid_width.max(0.001);
+        uniforms.neighbor_radius = settings.neighbor_radius.clamp(1, MAX_NEIGHBOR_RADIUS);
+        uniforms.min_birth = settings.birth_range.min;
+        uniforms.max_birth = settings.birth_range.max;
+        uniforms.min_survive = settings.survive_range.min;
+        uniforms.max_survive = settings.survive_range.max;
+        uniforms.glow_radius = settings.glow_radius.clamp(1.0, max_glow_radius);
+        uniforms.glow_intensity = settings.glow_intensity.max(0.0);
+        let uniforms_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let phase_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("phase buffer"),
+            contents: bytemuck::cast_slice(&[0u32, 1u32]),
+            usage: wgpu::BufferUsages::COPY_SRC,
+        });
+
+        let uniforms_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: None,
+            });
+        let uniforms_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &uniforms_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniforms_buffer.as_entire_binding(),
+            }],
+            label: None,
+        });
+
+        // Create pipeline
+        let source = shader::Source::assemble(None).map_err(InitError::Shader)?;
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.text.as_str().into()),
+        });
+        if let Some(e) = device.pop_error_scope().await {
+            return Err(InitError::Shader(source.annotate(&e.to_string())));
+        }
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &texture_bind_group_layout,
+                    &uniforms_bind_group_layout,
+                    &rule_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &texture_target_bind_group_layout,
+                    &uniforms_bind_group_layout,
+                    // For previewing the next generation
+                    &rule_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let compute_pipeline =
+            create_compute_pipeline(&device, &compute_pipeline_layout, &shader, cell_format);
+
+        let normalize_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Normalize Pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_compute",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_normalize",
+                targets: &[Some(cell_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: msaa_samples,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let clear_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Clear Pipeline Layout"),
+                bind_group_layouts: &[],
+                push_constant_ranges: &[],
+            });
+
+        let clear_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Clear Pipeline"),
+            layout: Some(&clear_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_compute",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_clear",
+                targets: &[Some(cell_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let mip_base_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Mip Base Pipeline Layout"),
+                bind_group_layouts: &[&texture_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let mip_base_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Mip Base Pipeline"),
+            layout: Some(&mip_base_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_compute",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_display_base",
+                targets: &[Some(DisplayMips::FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let downsample_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Downsample Pipeline Layout"),
+                bind_group_layouts: &[&downsample_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let downsample_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Downsample Pipeline"),
+            layout: Some(&downsample_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_compute",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_downsample",
+                targets: &[Some(DisplayMips::FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let glow_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Glow Pipeline Layout"),
+            bind_group_layouts: &[&downsample_bind_group_layout, &uniforms_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let [glow_horizontal_pipeline, glow_vertical_pipeline] =
+            ["fs_glow_horizontal", "fs_glow_vertical"].map(|entry_point| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("Glow Pipeline"),
+                    layout: Some(&glow_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_compute",
+                        buffers: &[],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point,
+                        targets: &[Some(DisplayMips::FORMAT.into())],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                })
+            });
+
+        // The draw pass only reads the uniforms, but they live in group 1 of the shared shader
+        let empty_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[],
+            });
+        let empty_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &empty_bind_group_layout,
+            entries: &[],
+        });
+        let draw_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Draw Pipeline Layout"),
+            bind_group_layouts: &[&empty_bind_group_layout, &uniforms_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let draw_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Draw Pipeline"),
+            layout: Some(&draw_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_compute",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_draw",
+                targets: &[Some(cell_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let doc = web_sys::window().unwrap().document().unwrap();
+        let tooltip = doc
+            .create_element("div")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap();
+        tooltip.set_class_name("tooltip");
+        canvas.set_class_name(InteractionMode::Draw.css_class());
+        tooltip.set_hidden(true);
+        doc.body().unwrap().append_child(&tooltip).unwrap();
+
+        let badge = doc
+            .create_element("div")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap();
+        badge.set_class_name("tooltip");
+        badge.set_hidden(true);
+        doc.body().unwrap().append_child(&badge).unwrap();
+
+        let help = doc
+            .create_element("div")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap();
+        help.set_class_name("help");
+        help.set_hidden(true);
+        doc.body().unwrap().append_child(&help).unwrap();
+
+        let live_region = doc
+            .create_element("div")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap();
+        live_region.set_class_name("visually-hidden");
+        live_region.set_attribute("aria-live", "polite").unwrap();
+        doc.body().unwrap().append_child(&live_region).unwrap();
+
+        let benchmark_output = doc
+            .create_element("p")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap();
+        benchmark_output.set_class_name("benchmark");
+        benchmark_output.set_hidden(true);
+        canvas.after_with_node_1(&benchmark_output).unwrap();
+
+        let extinct_message = doc
+            .create_element("p")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap();
+        extinct_message.set_class_name("extinct");
+        extinct_message.set_hidden(true);
+        canvas.after_with_node_1(&extinct_message).unwrap();
+
+        let frame_stats_output = doc
+            .create_element("p")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap();
+        frame_stats_output.set_class_name("frame-stats");
+        frame_stats_output.set_hidden(true);
+        canvas.after_with_node_1(&frame_stats_output).unwrap();
+
+        let population_graph = doc
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        population_graph.set_class_name("population-graph");
+        population_graph.set_hidden(true);
+        // Sized in device pixels so the lines stay sharp
+        let ratio = web_sys::window().unwrap().device_pixel_ratio();
+        population_graph.set_width((POPULATION_GRAPH_SIZE.0 as f64 * ratio) as u32);
+        population_graph.set_height((POPULATION_GRAPH_SIZE.1 as f64 * ratio) as u32);
+        population_graph
+            .style()
+            .set_property("width", &format!("{}px", POPULATION_GRAPH_SIZE.0))
+            .unwrap();
+        population_graph
+            .style()
+            .set_property("height", &format!("{}px", POPULATION_GRAPH_SIZE.1))
+            .unwrap();
+        canvas.after_with_node_1(&population_graph).unwrap();
+
+        let mut state = Self {
+            msaa_samples,
+            msaa_view: RwLock::new(msaa_view),
+            surface,
+            device,
+            queue,
+            config: RwLock::new(config),
+            compute_pipeline: RwLock::new(compute_pipeline),
+            compute_pipeline_layout,
+            custom_rule: RwLock::new(None),
+            normalize_pipeline,
+            render_pipeline,
+            clear_pipeline,
+            draw_pipeline,
+            empty_bind_group,
+            mip_base_pipeline,
+            downsample_pipeline,
+            display_mips,
+            glow_horizontal_pipeline,
+            glow_vertical_pipeline,
+            glow,
+            paused: RwLock::new(!settings.autostart),
+            generation: RwLock::new(0),
+            max_generations: RwLock::new(
+                settings
+                    .max_generations
+                    .map(|max| settings.warmup as u64 + max as u64),
+            ),
+            warmup: settings.warmup,
+            target_steps_per_frame: RwLock::new(1),
+            steps_per_frame: RwLock::new(1),
+            turbo_steps: settings.turbo_steps,
+            noise_flips: settings.noise_flips,
+            stamp_interval: settings.stamp_interval,
+            scheduled_stamps: RwLock::new(Vec::new()),
+            last_pattern: RwLock::new(None),
+            last_frame_time: RwLock::new(None),
+            frame_delta: RwLock::new(0.0),
+            hidden: RwLock::new(false),
+            default_settings: String::new(),
+            canvas: canvas.clone(),
+            tooltip,
+            badge,
+            badge_until: RwLock::new(0.0),
+            help,
+            help_states: RwLock::new(Vec::new()),
+            live_region,
+            benchmark_output,
+            extinct_message,
+            population_graph,
+            frame_stats: RwLock::new(FrameStats::default()),
+            frame_stats_output,
+            population_history: RwLock::new(VecDeque::new()),
+            population_window: settings.population_window,
+            pause_on_extinction: settings.pause_on_extinction,
+            pause_on_blur: settings.pause_on_blur,
+            draw_pauses: settings.draw_pauses,
+            fit_pattern_margin: settings.fit_pattern_margin,
+            quiet_zone: RwLock::new(settings.quiet_zone.min(MAX_QUIET_ZONE)),
+            blur_paused: RwLock::new(false),
+            draw_paused: RwLock::new(false),
+            benchmarking: RwLock::new(false),
+            edit_cursor: RwLock::new(None),
+            inspect: RwLock::new(false),
+            inspecting: RwLock::new(false),
+            last_inspection: RwLock::new(0.0),
+            searching: RwLock::new(false),
+            capturing_gif: RwLock::new(false),
+            gif_frames: settings.gif_frames,
+            gif_step: settings.gif_step,
+            video_seconds: settings.video_seconds,
+            video: RwLock::new(None),
+            mousedown: RwLock::new(false),
+            interaction_mode: RwLock::new(InteractionMode::Draw),
+            view_tween: RwLock::new(None),
+            flip_y: RwLock::new(settings.flip_y),
+            kill_margin: settings.kill_margin,
+            recording: RwLock::new(None),
+            context_lost: RwLock::new(false),
+            jumping: RwLock::new(false),
+            snapshot: RwLock::new(None),
+            slots: RwLock::new(HashMap::new()),
+            poke: RwLock::new(false),
+            line_mode: RwLock::new(false),
+            anchor: RwLock::new(None),
+            panning: RwLock::new(false),
+            selecting: RwLock::new(false),
+            selection: RwLock::new(None),
+            clipboard: RwLock::new(None),
+            last_mousepos: RwLock::new(None),
+            last_screenpos: RwLock::new(None),
+            prev_mousepos: RwLock::new(None),
+            taper: RwLock::new(false),
+            taper_mousepos: RwLock::new(None),
+            brush_shape: RwLock::new(BrushShape::Circle),
+            spray_density: RwLock::new(0.1),
+            sim_kind: RwLock::new(SimKind::Classic),
+            start_mousepos: RwLock::new(None),
+            texture_size,
+            cell_format,
+            layers,
+            dual_layer: RwLock::new(false),
+            texture_target_bind_group,
+            palette_texture,
+            palette: RwLock::new(Palette::default()),
+            palette_preset: RwLock::new(PalettePreset::Default),
+            previous_texture,
+            classes_texture,
+            interpolate: RwLock::new(false),
+            interpolation_ms: settings.interpolation_ms,
+            step_interval_ms: RwLock::new(settings.step_interval_ms),
+            draw_interval_ms: settings.draw_interval_ms,
+            last_step_time: RwLock::new(0.0),
+            last_draw_time: RwLock::new(f64::NEG_INFINITY),
+            birth_animation_ms: settings.birth_animation_ms,
+            uniforms: RwLock::new(uniforms),
+            uniforms_buffer,
+            phase_buffer,
+            uniforms_bind_group,
+        };
+        if let Some(preset) = settings.palette_preset {
+            state.apply_palette_preset(&mut state.uniforms.write().unwrap(), preset);
+        }
+        if settings.palette != Palette::default() {
+            state.set_palette(&mut state.uniforms.write().unwrap(), settings.palette);
+        }
+        // Every texture the passes read starts out defined, as dead cells
+        state.clear_board();
+        if let Some(body) = settings.rule_wgsl {
+            if let Err(e) = state.set_custom_rule(&body).await {
+                error!("Invalid data-rule-wgsl, keeping the default rules: {}", e);
+            }
+        }
+        if let Some(gradient) = settings.initial_fill {
+            let seed = settings
+                .initial_seed
+                .unwrap_or_else(|| js_sys::Date::now() as u64);
+            state.randomize(gradient, seed);
+        }
+        state.default_settings = state.serialize_settings();
+
+        Ok(state)
+    }
+
+    async fn input(self: &Arc<Self>, event: &CanvasEvent) -> bool {
+        warn!("{:?}", &event);
+        if *self.hidden.read().unwrap()
+            && matches!(event, CanvasEvent::MouseMove(..) | CanvasEvent::Wheel(..))
+        {
+            // Stale pointer input queued up while the tab was in the background
+            return false;
+        }
+        self.record(event);
+        // Nearly every key switches some setting, frames before it say nothing about the new one
+        match event {
+            CanvasEvent::KeyDown { key, .. } if key != "F" => self.reset_frame_stats(),
+            CanvasEvent::SpeedWheel(..) => self.reset_frame_stats(),
+            _ => {}
+        }
+        match event {
+            CanvasEvent::Play => self.set_paused(false),
+            CanvasEvent::Pause => self.set_paused(true),
+            CanvasEvent::Step => self.single_step(),
+            CanvasEvent::Clear => self.clear_board(),
+            CanvasEvent::SaveSlot(slot) => self.save_slot(*slot).await,
+            CanvasEvent::RestoreSlot(slot) => self.restore_slot(*slot),
+            CanvasEvent::Resize => self.fit_canvas(),
+            CanvasEvent::ResetView => self.reset_view(),
+            CanvasEvent::InjectNoise(flips) => self.inject_noise(*flips).await,
+            CanvasEvent::ContextLost => {
+                error!("Lost the graphics context, waiting for it to be restored");
+                *self.context_lost.write().unwrap() = true;
+                *self.searching.write().unwrap() = false;
+            }
+            // Handled by the event loop, which replaces the whole state
+            CanvasEvent::ContextRestored | CanvasEvent::Reconfigure(..) => {}
+            CanvasEvent::Randomize(gradient, seed) => {
+                self.randomize(*gradient, seed.unwrap_or(js_sys::Date::now() as u64));
+                self.warm_up();
+            }
+            CanvasEvent::Visibility(visible) => {
+                *self.hidden.write().unwrap() = !visible;
+                if *visible {
+                    // The button may have been released elsewhere, and the first frame after
+                    // resuming must not be measured against the last one before hiding
+                    *self.mousedown.write().unwrap() = false;
+                    *self.selecting.write().unwrap() = false;
+                    *self.last_frame_time.write().unwrap() = None;
+                }
+            }
+            CanvasEvent::Focus(false) if self.pause_on_blur && !*self.paused.read().unwrap() => {
+                self.set_paused(true);
+                *self.blur_paused.write().unwrap() = true;
+            }
+            CanvasEvent::Focus(true) => {
+                *self.mousedown.write().unwrap() = false;
+                *self.last_frame_time.write().unwrap() = None;
+                // A stroke interrupted by losing focus has ended without a MouseUp
+                let resume = std::mem::take(&mut *self.blur_paused.write().unwrap())
+                    | std::mem::take(&mut *self.draw_paused.write().unwrap());
+                if resume {
+                    self.set_paused(false);
+                }
+            }
+            CanvasEvent::MouseDown { shift, alt } if *self.poke.read().unwrap() && !*shift => {
+                let mode = *self.interaction_mode.read().unwrap();
+                if (mode == InteractionMode::Pan) != *alt {
+                    *self.mousedown.write().unwrap() = true;
+                    *self.panning.write().unwrap() = true;
+                } else {
+                    // Copied out, the lock must not be held across the readback
+                    let cell = *self.last_mousepos.read().unwrap();
+                    if let Some((x, y)) = cell {
+                        if let Err(e) = self.toggle_cell(x, y).await {
+                            warn!("Could not toggle the cell: {}", e);
+                        }
+                    }
+                }
+            }
+            CanvasEvent::MouseDown { shift, alt } if *self.line_mode.read().unwrap() && !*shift => {
+                let mode = *self.interaction_mode.read().unwrap();
+                if (mode == InteractionMode::Pan) != *alt {
+                    *self.mousedown.write().unwrap() = true;
+                    *self.panning.write().unwrap() = true;
+                } else if let Some(cell) = *self.last_mousepos.read().unwrap() {
+                    let anchor = self.anchor.write().unwrap().take();
+                    match anchor {
+                        Some(anchor) => self.draw_line(anchor, cell),
+                        None => *self.anchor.write().unwrap() = Some(cell),
+                    }
+                }
+            }
+            CanvasEvent::MouseDown { shift, alt } => {
+                *self.mousedown.write().unwrap() = true;
+                *self.start_mousepos.write().unwrap() = *self.last_mousepos.read().unwrap();
+                *self.selecting.write().unwrap() = *shift;
+                *self.selection.write().unwrap() = None;
+                // Alt swaps the plain drag action for the other one
+                let mode = *self.interaction_mode.read().unwrap();
+                *self.panning.write().unwrap() =
+                    !*shift && ((mode == InteractionMode::Pan) != *alt);
+                if !*shift && !*self.panning.read().unwrap() {
+                    self.extinct_message.set_hidden(true);
+                    if self.draw_pauses && !*self.paused.read().unwrap() {
+                        *self.paused.write().unwrap() = true;
+                        *self.draw_paused.write().unwrap() = true;
+                    }
+                }
+            }
+            CanvasEvent::MouseUp => {
+                *self.mousedown.write().unwrap() = false;
+                *self.panning.write().unwrap() = false;
+                if std::mem::take(&mut *self.draw_paused.write().unwrap()) {
+                    *self.paused.write().unwrap() = false;
+                }
+                if *self.selecting.read().unwrap() {
+                    *self.selecting.write().unwrap() = false;
+                    *self.selection.write().unwrap() = self.drag_rect();
+                }
+            }
+            CanvasEvent::LoadPattern(pattern, at) => {
+                let (x, y) = at
+                    .map(|at| self.screen_to_cell(at))
+                    .unwrap_or((self.texture_size.width / 2, self.texture_size.height / 2));
+                let origin = (
+                    x as i32 - pattern.width as i32 / 2,
+                    y as i32 - pattern.height as i32 / 2,
+                );
+                // Stamping writes dead cells too, so the padding clears the quiet zone
+                let margin = *self.quiet_zone.read().unwrap();
+                self.stamp(
+                    &pattern.padded(margin),
+                    origin.0 - margin as i32,
+                    origin.1 - margin as i32,
+                );
+                *self.last_pattern.write().unwrap() = Some(pattern.clone());
+                if let Some(margin) = self.fit_pattern_margin {
+                    self.fit_view(
+                        [origin.0 as f32, origin.1 as f32],
+                        [pattern.width as f32, pattern.height as f32],
+                        margin,
+                    );
+                }
+                self.warm_up();
+            }
+            CanvasEvent::MouseLeave => {
+                self.tooltip.set_hidden(true);
+                *self.last_mousepos.write().unwrap() = None;
+                *self.last_screenpos.write().unwrap() = None;
+            }
+            CanvasEvent::KeyDown { key, ctrl } => {
+                let Some(binding) = BINDINGS.iter().find(|binding| binding.matches(key, *ctrl))
+                else {
+                    return false;
+                };
+                match binding.action {
+                    Action::Help => {
+                        self.help.set_hidden(!self.help.hidden());
+                    }
+                    Action::ResetView => self.reset_view(),
+                    Action::Cancel => {
+                        *self.selection.write().unwrap() = None;
+                        *self.edit_cursor.write().unwrap() = None;
+                        *self.anchor.write().unwrap() = None;
+                        self.uniforms.write().unwrap().paste_size = [0.0; 2];
+                    }
+                    Action::MoveCursor => {
+                        let (dx, dy) = match key.as_str() {
+                            "ArrowLeft" => (-1, 0),
+                            "ArrowRight" => (1, 0),
+                            "ArrowUp" => (0, -1),
+                            _ => (0, 1),
+                        };
+                        self.move_edit_cursor(dx, dy);
+                    }
+                    Action::Pause | Action::ToggleCell
+                        if self.edit_cursor.read().unwrap().is_some() =>
+                    {
+                        let (x, y) = self.edit_cursor.read().unwrap().unwrap();
+                        match self.toggle_cell(x, y).await {
+                            Ok(alive) => self.announce(&format!(
+                                "Cell {}, {} is now {}",
+                                x,
+                                y,
+                                if alive { "alive" } else { "dead" }
+                            )),
+                            Err(e) => warn!("Could not toggle the cell: {}", e),
+                        }
+                    }
+                    Action::Record => {
+                        if self.recording.read().unwrap().is_some() {
+                            let log = self.stop_recording();
+                            let storage = web_sys::window().unwrap().local_storage();
+                            if let Ok(Some(storage)) = storage {
+                                let _ = storage.set_item(MACRO_STORAGE_KEY, &log);
+                            }
+                            warn!("Recorded {} events", log.lines().count());
+                        } else {
+                            self.start_recording();
+                            warn!("Recording input, press r again to stop");
+                        }
+                    }
+                    Action::Replay => {
+                        let storage = web_sys::window().unwrap().local_storage();
+                        match storage
+                            .ok()
+                            .flatten()
+                            .and_then(|s| s.get_item(MACRO_STORAGE_KEY).ok())
+                        {
+                            Some(Some(log)) => {
+                                let state = Arc::clone(self);
+                                wasm_bindgen_futures::spawn_local(async move {
+                                    state.replay(&log).await;
+                                });
+                            }
+                            _ => warn!("Nothing recorded yet, press r to start recording"),
+                        }
+                    }
+                    Action::StepSelection => {
+                        self.step_selection();
+                    }
+                    Action::ClearSelection => {
+                        let selection = *self.selection.read().unwrap();
+                        if let Some(selection) = selection {
+                            self.clear_region(selection);
+                        }
+                    }
+                    Action::Copy => {
+                        let selection = *self.selection.read().unwrap();
+                        if let Some((x0, y0, x1, y1)) = selection {
+                            let (width, height) = (x1 - x0 + 1, y1 - y0 + 1);
+                            let data = match self.read_region(x0, y0, width, height).await {
+                                Ok(data) => data,
+                                Err(e) => {
+                                    warn!("Could not copy the selection: {}", e);
+                                    return false;
+                                }
+                            };
+                            *self.clipboard.write().unwrap() = Some((data, width, height));
+                            let mut uniforms = self.uniforms.write().unwrap();
+                            uniforms.paste_size = [width as f32, height as f32];
+                            uniforms.paste_corner = 0;
+                        }
+                    }
+                    Action::Transform => self.transform_clipboard(key),
+                    Action::Video => self.toggle_video(),
+                    Action::ExportGif => {
+                        let state = Arc::clone(self);
+                        wasm_bindgen_futures::spawn_local(async move {
+                            match state.export_gif(state.gif_frames, state.gif_step).await {
+                                Ok(gif) => download(&gif, "image/gif", "life.gif"),
+                                Err(e) => warn!("{}", e),
+                            }
+                        });
+                    }
+                    Action::Classify => self.classify().await,
+                    Action::Jump => self.jump_prompt(),
+                    Action::Export => {
+                        let answer = web_sys::window().unwrap().prompt_with_message_and_default(
+                            "Export the board as rle, life106 or cells",
+                            "rle",
+                        );
+                        if let Ok(Some(answer)) = answer {
+                            match answer.parse::<Format>() {
+                                Ok(format) => match self.board_pattern().await {
+                                    Ok(pattern) => download(
+                                        pattern.export(format).as_bytes(),
+                                        "text/plain",
+                                        &format!("life.{}", format.extension()),
+                                    ),
+                                    Err(e) => warn!("Could not export the board: {}", e),
+                                },
+                                Err(e) => warn!("{}", e),
+                            }
+                        }
+                    }
+                    Action::BrushShape => {
+                        let shape = self.brush_shape.read().unwrap().next();
+                        *self.brush_shape.write().unwrap() = shape;
+                        self.uniforms.write().unwrap().brush_shape = shape as u32;
+                        warn!("Brush shape: {:?}", shape);
+                    }
+                    Action::Pause => {
+                        let paused = !*self.paused.read().unwrap();
+                        self.set_paused(paused);
+                    }
+                    Action::Step => {
+                        self.single_step();
+                    }
+                    Action::Inspect => {
+                        let inspect = !*self.inspect.read().unwrap();
+                        *self.inspect.write().unwrap() = inspect;
+                        if !inspect {
+                            self.tooltip.set_hidden(true);
+                        }
+                    }
+                    Action::Turbo => {
+                        let mut target = self.target_steps_per_frame.write().unwrap();
+                        *target = if *target == 1 { self.turbo_steps } else { 1 };
+                        warn!("{} steps per frame", *target);
+                    }
+                    Action::Poke => {
+                        let poke = !*self.poke.read().unwrap();
+                        *self.poke.write().unwrap() = poke;
+                        *self.line_mode.write().unwrap() = false;
+                        *self.anchor.write().unwrap() = None;
+                        warn!(
+                            "Clicking {}",
+                            if poke {
+                                "toggles single cells"
+                            } else {
+                                "paints with the brush"
+                            }
+                        );
+                    }
+                    Action::LineMode => {
+                        let line_mode = !*self.line_mode.read().unwrap();
+                        *self.line_mode.write().unwrap() = line_mode;
+                        *self.poke.write().unwrap() = false;
+                        *self.anchor.write().unwrap() = None;
+                        warn!(
+                            "Clicking {}",
+                            if line_mode {
+                                "twice draws a straight line, Escape cancels"
+                            } else {
+                                "paints with the brush"
+                            }
+                        );
+                    }
+                    Action::WriteSettings => {
+                        let settings = self.serialize_settings();
+                        let location = web_sys::window().unwrap().location();
+                        let _ = location.set_hash(&settings);
+                        warn!("Settings: {}", settings);
+                    }
+                    Action::InteractionMode => {
+                        let mode = match *self.interaction_mode.read().unwrap() {
+                            InteractionMode::Draw => InteractionMode::Pan,
+                            InteractionMode::Pan => InteractionMode::Draw,
+                        };
+                        *self.interaction_mode.write().unwrap() = mode;
+                        self.canvas.set_class_name(mode.css_class());
+                        warn!("Dragging will {:?}, hold Alt to do the other", mode);
+                    }
+                    Action::CellStyle => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.cell_style = 1 - uniforms.cell_style;
+                        warn!(
+                            "Drawing cells as {}",
+                            if uniforms.cell_style == 1 {
+                                "circles"
+                            } else {
+                                "squares"
+                            }
+                        );
+                    }
+                    Action::KillMargin => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.kill_margin = if uniforms.kill_margin == 0 {
+                            self.kill_margin
+                        } else {
+                            0
+                        };
+                        match uniforms.kill_margin {
+                            0 => warn!("Cells wrap around the edges"),
+                            n => warn!("Cells within {} of the edges die", n),
+                        }
+                    }
+                    Action::Brick => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.brick = 1 - uniforms.brick;
+                        warn!(
+                            "{}",
+                            if uniforms.brick == 1 {
+                                "Shifting every other row by half a cell"
+                            } else {
+                                "Cells in a square grid"
+                            }
+                        );
+                    }
+                    Action::ScrollPastEdges => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.scrolling = 1 - uniforms.scrolling;
+                        if uniforms.scrolling == 1 {
+                            warn!("Scrolling around the wrapping board");
+                        } else {
+                            // Back to the cells where they are stored
+                            uniforms.content_offset = [0.0; 2];
+                            warn!("Stopping at the edges of the board");
+                        }
+                    }
+                    Action::Boundary => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.boundary_mode = (uniforms.boundary_mode + 1) % 3;
+                        match uniforms.boundary_mode {
+                            BOUNDARY_TORUS => warn!("Edges: the board wraps around"),
+                            BOUNDARY_DEAD => warn!("Edges: everything past them is dead"),
+                            _ => warn!("Edges: neighbors are mirrored at them"),
+                        }
+                    }
+                    Action::Neighborhood => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.neighborhood = (uniforms.neighborhood + 1) % 2;
+                        match uniforms.neighborhood {
+                            NEIGHBORHOOD_MOORE => {
+                                warn!("Neighborhood: Moore, the eight cells around")
+                            }
+                            _ => warn!("Neighborhood: von Neumann, the four orthogonal cells"),
+                        }
+                    }
+                    Action::UpdateScheme => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.update_scheme = (uniforms.update_scheme + 1) % 2;
+                        match uniforms.update_scheme {
+                            UPDATE_SYNCHRONOUS => {
+                                warn!("Update scheme: synchronous, every cell at once")
+                            }
+                            _ => warn!(
+                                "Update scheme: checkerboard, alternating halves of the cells"
+                            ),
+                        }
+                    }
+                    Action::Channel => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.view_channel = (uniforms.view_channel + 1) % 5;
+                        match uniforms.view_channel {
+                            0 => warn!("Showing cells normally"),
+                            n => warn!(
+                                "Showing the {} channel",
+                                ["R", "G", "B", "A"][n as usize - 1]
+                            ),
+                        }
+                    }
+                    Action::Smoothing => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.filtered_zoom = 1 - uniforms.filtered_zoom;
+                        warn!(
+                            "{} board when zoomed out",
+                            if uniforms.filtered_zoom == 1 {
+                                "Smoothing"
+                            } else {
+                                "Point sampling"
+                            }
+                        );
+                    }
+                    Action::Blend => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.smooth_zoom = 1 - uniforms.smooth_zoom;
+                        warn!(
+                            "{} cells when zoomed in",
+                            if uniforms.smooth_zoom == 1 {
+                                "Smoothing"
+                            } else {
+                                "Sharp edges between"
+                            }
+                        );
+                    }
+                    Action::Glow => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.glow = 1 - uniforms.glow;
+                        warn!("Glow {}", if uniforms.glow == 1 { "on" } else { "off" });
+                    }
+                    Action::Grid => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.major_grid_interval = match uniforms.major_grid_interval {
+                            0 => 8,
+                            8 => 16,
+                            16 => 32,
+                            _ => 0,
+                        };
+                        match uniforms.major_grid_interval {
+                            0 => warn!("Gridlines off"),
+                            n => warn!("Major gridlines every {} cells", n),
+                        }
+                    }
+                    Action::Minimap => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.minimap = 1 - uniforms.minimap;
+                    }
+                    Action::SimKind => {
+                        let sim_kind = match *self.sim_kind.read().unwrap() {
+                            SimKind::Classic => SimKind::SmoothLife,
+                            SimKind::SmoothLife | SimKind::Custom => SimKind::Classic,
+                        };
+                        *self.sim_kind.write().unwrap() = sim_kind;
+                        self.uniforms.write().unwrap().sim_kind = sim_kind as u32;
+                        self.normalize_channels();
+                        warn!("Simulation: {:?}", sim_kind);
+                    }
+                    Action::BrushSize => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        let factor = if key == "]" { 1.25 } else { 0.8 };
+                        uniforms.brush_radius = (uniforms.brush_radius * factor).clamp(1.0, 64.0);
+                        warn!("Brush radius: {}", uniforms.brush_radius);
+                    }
+                    Action::Taper => {
+                        let mut taper = self.taper.write().unwrap();
+                        *taper = !*taper;
+                        warn!(
+                            "Brush {}",
+                            if *taper {
+                                "tapers with the speed of the stroke"
+                            } else {
+                                "keeps its size"
+                            }
+                        );
+                    }
+                    Action::GrowIn => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.birth_animation_ms = if uniforms.birth_animation_ms > 0.0 {
+                            warn!("Birth animation off");
+                            0.0
+                        } else {
+                            warn!("Newborn cells grow over {}ms", self.birth_animation_ms);
+                            self.birth_animation_ms
+                        };
+                    }
+                    Action::Palette => {
+                        let preset = *self.palette_preset.read().unwrap();
+                        let i = PalettePreset::ALL
+                            .iter()
+                            .position(|p| *p == preset)
+                            .unwrap();
+                        let preset = PalettePreset::ALL[(i + 1) % PalettePreset::ALL.len()];
+                        self.apply_palette_preset(&mut self.uniforms.write().unwrap(), preset);
+                        warn!("Palette: {}", preset);
+                    }
+                    Action::Fullscreen => self.toggle_fullscreen(),
+                    Action::Stats => {
+                        let shown = self.frame_stats_output.hidden();
+                        self.frame_stats_output.set_hidden(!shown);
+                        warn!(
+                            "Frame statistics {}",
+                            if shown { "shown" } else { "hidden" }
+                        );
+                        self.show_frame_stats();
+                    }
+                    Action::PopulationGraph => {
+                        let shown = self.population_graph.hidden();
+                        self.population_graph.set_hidden(!shown);
+                        warn!(
+                            "Population graph {}",
+                            if shown { "shown" } else { "hidden" }
+                        );
+                        if shown {
+                            self.sample_population().await;
+                        }
+                    }
+                    Action::ShowChanges => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.show_changes = 1 - uniforms.show_changes;
+                        warn!(
+                            "Highlighting changed cells {}",
+                            if uniforms.show_changes == 1 {
+                                "on"
+                            } else {
+                                "off"
+                            }
+                        );
+                    }
+                    Action::Preview => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.predict = 1 - uniforms.predict;
+                        warn!(
+                            "Previewing the next generation {}",
+                            if uniforms.predict == 1 { "on" } else { "off" }
+                        );
+                    }
+                    Action::Snap => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.snap = 1 - uniforms.snap;
+                        warn!(
+                            "Snapping the brush to cell centers {}",
+                            if uniforms.snap == 1 { "on" } else { "off" }
+                        );
+                    }
+                    Action::SlowMotion => {
+                        let mut interpolate = self.interpolate.write().unwrap();
+                        *interpolate = !*interpolate;
+                        if *interpolate {
+                            warn!(
+                                "Slow motion, crossfading one generation per {}ms",
+                                self.interpolation_ms
+                            );
+                        } else {
+                            warn!("Slow motion off");
+                        }
+                    }
+                    Action::BrushWrap => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.brush_wrap = (uniforms.brush_wrap + 1) % 3;
+                        match uniforms.brush_wrap {
+                            BRUSH_WRAP_AUTO => {
+                                warn!("The brush wraps around whenever the board does")
+                            }
+                            BRUSH_WRAP_ALWAYS => warn!("The brush always wraps around the edges"),
+                            _ => warn!("The brush stops at the edges"),
+                        }
+                    }
+                    Action::Counter => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        uniforms.counter_corner = (uniforms.counter_corner + 1) % 5;
+                        match uniforms.counter_corner {
+                            0 => warn!("Generation counter hidden"),
+                            1 => warn!("Generation counter in the top-left corner"),
+                            2 => warn!("Generation counter in the top-right corner"),
+                            3 => warn!("Generation counter in the bottom-left corner"),
+                            _ => warn!("Generation counter in the bottom-right corner"),
+                        }
+                    }
+                    Action::Spray => {
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        if uniforms.brush_density < 1.0 {
+                            uniforms.brush_density = 1.0;
+                            warn!("Solid brush");
+                        } else {
+                            uniforms.brush_density = *self.spray_density.read().unwrap();
+                            warn!(
+                                "Spraying {:.0}% of the brush",
+                                uniforms.brush_density * 100.0
+                            );
+                        }
+                    }
+                    Action::SprayDensity => {
+                        let mut density = self.spray_density.write().unwrap();
+                        let step = if key == "}" { 0.05 } else { -0.05 };
+                        *density = (*density + step).clamp(0.05, 0.95);
+                        let mut uniforms = self.uniforms.write().unwrap();
+                        if uniforms.brush_density < 1.0 {
+                            uniforms.brush_density = *density;
+                        }
+                        warn!("Spray density: {:.0}%", *density * 100.0);
+                    }
+                    Action::Layers => {
+                        let dual_layer = !*self.dual_layer.read().unwrap();
+                        *self.dual_layer.write().unwrap() = dual_layer;
+                        self.uniforms.write().unwrap().layers = if dual_layer { 2 } else { 1 };
+                        if dual_layer {
+                            warn!(
+                                "Simulating two layers with {} and {}",
+                                self.layers[0].rule.read().unwrap(),
+                                self.layers[1].rule.read().unwrap()
+                            );
+                        } else {
+                            warn!("Simulating one layer");
+                        }
+                    }
+                    Action::Noise => self.inject_noise(self.noise_flips).await,
+                    Action::Stamp => {
+                        let pattern = self.last_pattern.read().unwrap().clone();
+                        let cell = *self.last_mousepos.read().unwrap();
+                        match (pattern, cell) {
+                            (Some(pattern), Some((x, y))) => {
+                                self.schedule_stamp(pattern, x, y, self.stamp_interval)
+                            }
+                            (None, _) => warn!("Load a pattern first, q stamps it repeatedly"),
+                            (_, None) => warn!("Point at the cell to stamp the pattern on"),
+                        }
+                    }
+                    Action::ClearStamps => {
+                        self.scheduled_stamps.write().unwrap().clear();
+                        warn!("Cleared the scheduled stamps");
+                    }
+                    Action::NextRule => {
+                        let rule = *self.layers[0].rule.read().unwrap();
+                        let next = Rule::PRESETS
+                            .iter()
+                            .position(|(_, preset)| *preset == rule)
+                            .map_or(0, |i| (i + 1) % Rule::PRESETS.len());
+                        let (name, rule) = Rule::PRESETS[next];
+                        self.layers[0].set_rule(&self.queue, rule);
+                        warn!("Rule: {} ({})", rule, name);
+                    }
+                    Action::Benchmark if !*self.benchmarking.read().unwrap() => {
+                        let state = Arc::clone(self);
+                        wasm_bindgen_futures::spawn_local(async move {
+                            state.benchmark().await;
+                        });
+                    }
+                    Action::SoupSearch => {
+                        let searching = !*self.searching.read().unwrap();
+                        *self.searching.write().unwrap() = searching;
+                        if searching {
+                            let state = Arc::clone(self);
+                            wasm_bindgen_futures::spawn_local(async move {
+                                state.soup_search().await;
+                            });
+                        }
+                    }
+                    Action::Paste => {
+                        let cursor = *self.last_mousepos.read().unwrap();
+                        if let Some((x, y)) = cursor {
+                            self.paste((x, y));
+                        }
+                    }
+                    // Without an edit cursor or while benchmarking, slots are events of their own
+                    Action::ToggleCell
+                    | Action::Benchmark
+                    | Action::SaveSlot
+                    | Action::RestoreSlot => {}
+                }
+            }
+            CanvasEvent::Wheel(delta) => {
+                let screen = self.last_screenpos.read().unwrap().unwrap_or((
+                    self.config.read().unwrap().width / 2,
+                    self.config.read().unwrap().height / 2,
+                ));
+                self.zoom_at(screen, 1.1f32.powf(-*delta as f32 / 100.0));
+                *self.last_mousepos.write().unwrap() = Some(self.screen_to_cell(screen));
+            }
+            CanvasEvent::SpeedWheel(delta) => {
+                let speed = {
+                    let mut target = self.target_steps_per_frame.write().unwrap();
+                    let scaled = *target as f64 * 2f64.powf(-delta / 100.0);
+                    // Rounded away from the current speed so small trackpad deltas still count
+                    let rounded = if *delta < 0.0 {
+                        scaled.ceil()
+                    } else {
+                        scaled.floor()
+                    };
+                    *target = (rounded as u32).clamp(1, MAX_SPEED);
+                    *target
+                };
+                warn!("{} steps per frame", speed);
+                self.show_speed(speed);
+            }
+            CanvasEvent::MouseMove(x, y) => {
+                let old_screenpos = self.last_screenpos.write().unwrap().replace((*x, *y));
+                if let (true, Some((old_x, old_y))) = (*self.panning.read().unwrap(), old_screenpos)
+                {
+                    self.pan_by(old_x as f32 - *x as f32, old_y as f32 - *y as f32);
+                }
+                let (x, y) = &self.screen_to_cell((*x, *y));
+                let old_mousepos = *self.last_mousepos.read().unwrap();
+                *self.last_mousepos.write().unwrap() = Some((*x, *y));
+                self.maybe_inspect();
+                if old_mousepos != Some((*x, *y)) {
+                    *self.prev_mousepos.write().unwrap() = old_mousepos;
+                }
+                if !*self.mousedown.read().unwrap() || old_mousepos.is_none() {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Fills the help with a row for every binding and what it is set to now, if it is shown.
+    /// Called on every update, so the table is only rebuilt when one of the settings changed.
+    fn show_help(&self) {
+        if self.help.hidden() {
+            return;
+        }
+        let states: Vec<_> = BINDINGS
+            .iter()
+            .map(|binding| binding.state.map_or(String::new(), |state| state(self)))
+            .collect();
+        if *self.help_states.read().unwrap() == states {
+            return;
+        }
+        let doc = web_sys::window().unwrap().document().unwrap();
+        let table = doc.create_element("table").unwrap();
+        for (binding, state) in BINDINGS.iter().zip(&states) {
+            let row = doc.create_element("tr").unwrap();
+            for text in [binding.label, binding.description, state] {
+                let cell = doc.create_element("td").unwrap();
+                cell.set_text_content(Some(text));
+                row.append_child(&cell).unwrap();
+            }
+            table.append_child(&row).unwrap();
+        }
+        self.help.set_text_content(None);
+        self.help.append_child(&table).unwrap();
+        *self.help_states.write().unwrap() = states;
+    }
+
+    fn update(&self) {
+        let MOUSE_INACTIVE = [-1000.0, 0.0];
+        // Every position handed to the shader is in cells, like the fragment positions of the
+        // passes over the board, so none of them depend on the canvas resolution
+        let cell_uniform =
+            |cell: Option<(u32, u32)>| cell.map_or(MOUSE_INACTIVE, |(x, y)| [x as f32, y as f32]);
+        // Snapped brushes are centered on the middle of the cell under the cursor instead of its
+        // corner, so they cover the highlighted cell symmetrically
+        let offset = if self.uniforms.read().unwrap().snap == 1 {
+            0.5
+        } else {
+            0.0
+        };
+        let brush_uniform = |cell: Option<(u32, u32)>| {
+            cell.map_or(MOUSE_INACTIVE, |(x, y)| {
+                [x as f32 + offset, y as f32 + offset]
+            })
+        };
+        let hover_pos = brush_uniform(*self.last_mousepos.read().unwrap());
+        let mut mousepos = hover_pos;
+        // The cell the stroke started on, it picks the stroke's color. `start_mousepos` outlives
+        // the stroke for the selection, the seed only lasts as long as the button is held down
+        let mut seed = cell_uniform(*self.start_mousepos.read().unwrap());
+
+        if !*self.mousedown.read().unwrap()
+            || *self.selecting.read().unwrap()
+            || *self.panning.read().unwrap()
+            || *self.capturing_gif.read().unwrap()
+        {
+            mousepos = MOUSE_INACTIVE;
+            seed = MOUSE_INACTIVE;
+        }
+
+        let selection = if *self.selecting.read().unwrap() {
+            self.drag_rect()
+        } else {
+            *self.selection.read().unwrap()
+        };
+        let selection = selection.map_or(SELECTION_INACTIVE, |(x0, y0, x1, y1)| {
+            [x0 as f32, y0 as f32, x1 as f32, y1 as f32]
+        });
+
+        debug!("{:?}", &mousepos);
+        self.uniforms.write().unwrap().time =
+            self.last_frame_time.read().unwrap().unwrap_or(0.0) as f32 / 1000.0;
+        self.uniforms.write().unwrap().generation = *self.generation.read().unwrap() as u32;
+        self.uniforms.write().unwrap().mouse_pos = mousepos;
+        self.uniforms.write().unwrap().hover_pos = hover_pos;
+        self.uniforms.write().unwrap().prev_mouse_pos = self
+            .prev_mousepos
+            .read()
+            .unwrap()
+            .map_or(hover_pos, |cell| brush_uniform(Some(cell)));
+        self.uniforms.write().unwrap().seed = seed;
+        self.uniforms.write().unwrap().selection = selection;
+        self.uniforms.write().unwrap().edit_cursor =
+            cell_uniform(*self.edit_cursor.read().unwrap());
+        self.uniforms.write().unwrap().anchor = cell_uniform(*self.anchor.read().unwrap());
+        self.queue.write_buffer(
+            &self.uniforms_buffer,
+            0,
+            bytemuck::cast_slice(&[*self.uniforms.read().unwrap()]),
+        );
+        self.show_help();
+    }
+
+    /// Kills every cell of every layer, so the first compute pass never reads uninitialized texels.
+    fn clear_board(&self) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        // The slow motion crossfade starts from it before the first step
+        let previous_view = self
+            .previous_texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let views = self
+            .layers
+            .iter()
+            .flat_map(|layer| [&layer.texture_view, &layer.texture_target_view])
+            .chain([&previous_view]);
+        for view in views {
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("clear board pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(BACKGROUND),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Compiles `body` as the body of `custom_rule` in `custom.wgsl` and switches to it. If it
+    /// does not compile the previous rule keeps running and the error, pointing into `body`, is
+    /// returned.
+    async fn set_custom_rule(&self, body: &str) -> Result<(), String> {
+        let source = shader::Source::assemble(Some(body))?;
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Custom rule shader"),
+                source: wgpu::ShaderSource::Wgsl(source.text.as_str().into()),
+            });
+        let pipeline = create_compute_pipeline(
+            &self.device,
+            &self.compute_pipeline_layout,
+            &shader,
+            self.cell_format,
+        );
+        if let Some(e) = self.device.pop_error_scope().await {
+            return Err(source.annotate(&e.to_string()));
+        }
+        *self.compute_pipeline.write().unwrap() = pipeline;
+        *self.custom_rule.write().unwrap() = Some(body.to_owned());
+        *self.sim_kind.write().unwrap() = SimKind::Custom;
+        self.uniforms.write().unwrap().sim_kind = SimKind::Custom as u32;
+        self.normalize_channels();
+        warn!("Simulation: custom rule");
+        Ok(())
+    }
+
+    /// Encodes everything about how the board is simulated and shown, but not the cells
+    /// themselves, as `key=value` pairs joined by `&` so it fits into a URL fragment.
+    fn serialize_settings(&self) -> String {
+        let uniforms = *self.uniforms.read().unwrap();
+        [
+            ("v", SETTINGS_VERSION.to_string()),
+            ("rule", self.layers[0].rule.read().unwrap().to_string()),
+            ("rule2", self.layers[1].rule.read().unwrap().to_string()),
+            ("layers", uniforms.layers.to_string()),
+            (
+                "speed",
+                self.target_steps_per_frame.read().unwrap().to_string(),
+            ),
+            (
+                "interval",
+                self.step_interval_ms.read().unwrap().to_string(),
+            ),
+            ("quiet", self.quiet_zone.read().unwrap().to_string()),
+            ("sim", format!("{:?}", *self.sim_kind.read().unwrap())),
+            ("outer", uniforms.outer_radius.to_string()),
+            ("inner", uniforms.inner_radius.to_string()),
+            ("sigmoid", uniforms.sigmoid_width.to_string()),
+            ("radius", uniforms.neighbor_radius.to_string()),
+            (
+                "birth",
+                format!("{}..{}", uniforms.min_birth, uniforms.max_birth),
+            ),
+            (
+                "survive",
+                format!("{}..{}", uniforms.min_survive, uniforms.max_survive),
+            ),
+            ("brush", uniforms.brush_radius.to_string()),
+            ("density", uniforms.brush_density.to_string()),
+            ("shape", format!("{:?}", *self.brush_shape.read().unwrap())),
+            ("style", uniforms.cell_style.to_string()),
+            ("brick", uniforms.brick.to_string()),
+            ("minimap", uniforms.minimap.to_string()),
+            ("grid", uniforms.major_grid_interval.to_string()),
+            ("margin", uniforms.kill_margin.to_string()),
+            ("counter", uniforms.counter_corner.to_string()),
+            ("brushwrap", uniforms.brush_wrap.to_string()),
+            ("boundary", uniforms.boundary_mode.to_string()),
+            ("neighborhood", uniforms.neighborhood.to_string()),
+            ("update", uniforms.update_scheme.to_string()),
+            ("preset", self.palette_preset.read().unwrap().to_string()),
+            ("palette", self.palette.read().unwrap().to_string()),
+            ("zoom", uniforms.zoom.to_string()),
+            ("x", uniforms.pan[0].to_string()),
+            ("y", uniforms.pan[1].to_string()),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+    }
+
+    /// Applies a string produced by `serialize_settings`. Unknown keys and invalid values are
+    /// skipped with a warning so the remaining fields still take effect.
+    fn apply_settings(&self, text: &str) {
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Option<T> {
+            let parsed = value.parse().ok();
+            if parsed.is_none() {
+                warn!("Ignoring invalid setting {}={:?}", key, value);
+            }
+            parsed
+        }
+
+        self.reset_frame_stats();
+        let mut normalize = false;
+        let text = migrate_settings(text, &self.default_settings);
+        let mut uniforms = self.uniforms.write().unwrap();
+        for pair in text.split('&').filter(|pair| !pair.is_empty()) {
+            let Some((key, value)) = pair.split_once('=') else {
+                warn!("Ignoring setting without a value: {:?}", pair);
+                continue;
+            };
+            match key {
+                // Taken care of by `migrate_settings`
+                "v" => {}
+                "rule" | "rule2" => {
+                    if let Some(rule) = parse::<Rule>(key, value) {
+                        self.layers[(key == "rule2") as usize].set_rule(&self.queue, rule);
+                        warn!("Rule of layer {}: {}", (key == "rule2") as usize + 1, rule);
+                    }
+                }
+                "layers" => {
+                    if let Some(layers) = parse::<u32>(key, value) {
+                        let dual_layer = layers == 2;
+                        *self.dual_layer.write().unwrap() = dual_layer;
+                        uniforms.layers = if dual_layer { 2 } else { 1 };
+                    }
+                }
+                "speed" => {
+                    if let Some(speed) = parse::<u32>(key, value) {
+                        *self.target_steps_per_frame.write().unwrap() = speed.clamp(1, MAX_SPEED);
+                    }
+                }
+                "quiet" => {
+                    if let Some(margin) = parse::<u32>(key, value) {
+                        *self.quiet_zone.write().unwrap() = margin.min(MAX_QUIET_ZONE);
+                    }
+                }
+                "interval" => {
+                    if let Some(interval) = parse::<f64>(key, value).filter(|i| i.is_finite()) {
+                        *self.step_interval_ms.write().unwrap() = interval.clamp(0.0, 60_000.0);
+                    }
+                }
+                "sim" => {
+                    let sim_kind = match value {
+                        "Classic" => SimKind::Classic,
+                        "SmoothLife" => SimKind::SmoothLife,
+                        "Custom" if self.custom_rule.read().unwrap().is_some() => SimKind::Custom,
+                        _ => {
+                            warn!("Ignoring invalid setting {}={:?}", key, value);
+                            continue;
+                        }
+                    };
+                    normalize |= *self.sim_kind.read().unwrap() != sim_kind;
+                    *self.sim_kind.write().unwrap() = sim_kind;
+                    uniforms.sim_kind = sim_kind as u32;
+                }
+                "outer" => {
+                    if let Some(radius) = parse::<f32>(key, value).filter(|r| r.is_finite()) {
+                        uniforms.outer_radius = radius.clamp(1.0, MAX_SMOOTH_RADIUS);
+                    }
+                }
+                "inner" => {
+                    if let Some(radius) = parse::<f32>(key, value).filter(|r| r.is_finite()) {
+                        uniforms.inner_radius = radius.clamp(0.0, MAX_SMOOTH_RADIUS);
+                    }
+                }
+                "sigmoid" => {
+                    if let Some(width) = parse::<f32>(key, value).filter(|w| w.is_finite()) {
+                        uniforms.sigmoid_width = width.clamp(0.001, 1.0);
+                    }
+                }
+                "radius" => {
+                    if let Some(radius) = parse::<u32>(key, value) {
+                        uniforms.neighbor_radius = radius.clamp(1, MAX_NEIGHBOR_RADIUS);
+                    }
+                }
+                "birth" => {
+                    if let Some(CountRange { min, max }) = parse(key, value) {
+                        uniforms.min_birth = min;
+                        uniforms.max_birth = max;
+                    }
+                }
+                "survive" => {
+                    if let Some(CountRange { min, max }) = parse(key, value) {
+                        uniforms.min_survive = min;
+                        uniforms.max_survive = max;
+                    }
+                }
+                "brush" => {
+                    if let Some(radius) = parse::<f32>(key, value).filter(|r| r.is_finite()) {
+                        uniforms.brush_radius = radius.clamp(1.0, 64.0);
+                    }
+                }
+                "density" => {
+                    if let Some(density) = parse::<f32>(key, value).filter(|d| d.is_finite()) {
+                        uniforms.brush_density = density.clamp(0.05, 1.0);
+                        if density < 1.0 {
+                            *self.spray_density.write().unwrap() = uniforms.brush_density;
+                        }
+                    }
+                }
+                "shape" => {
+                    let shape = match value {
+                        "Circle" => BrushShape::Circle,
+                        "Square" => BrushShape::Square,
+                        "Line" => BrushShape::Line,
+                        _ => {
+                            warn!("Ignoring invalid setting {}={:?}", key, value);
+                            continue;
+                        }
+                    };
+                    *self.brush_shape.write().unwrap() = shape;
+                    uniforms.brush_shape = shape as u32;
+                }
+                "style" => {
+                    if let Some(style) = parse::<u32>(key, value) {
+                        uniforms.cell_style = style.min(1);
+                    }
+                }
+                "brick" => {
+                    if let Some(brick) = parse::<u32>(key, value) {
+                        uniforms.brick = brick.min(1);
+                    }
+                }
+                "minimap" => {
+                    if let Some(minimap) = parse::<u32>(key, value) {
+                        uniforms.minimap = minimap.min(1);
+                    }
+                }
+                "grid" => {
+                    if let Some(interval) = parse::<u32>(key, value) {
+                        uniforms.major_grid_interval = interval.min(1024);
+                    }
+                }
+                "margin" => {
+                    if let Some(margin) = parse::<u32>(key, value) {
+                        uniforms.kill_margin = margin.min(self.texture_size.width / 2);
+                    }
+                }
+                "preset" => {
+                    if let Some(preset) = parse(key, value) {
+                        self.apply_palette_preset(&mut uniforms, preset);
+                    }
+                }
+                "palette" => {
+                    if let Some(palette) = parse(key, value) {
+                        self.set_palette(&mut uniforms, palette);
+                    }
+                }
+                "brushwrap" => {
+                    if let Some(wrap) = parse::<u32>(key, value).filter(|w| *w <= BRUSH_WRAP_NEVER)
+                    {
+                        uniforms.brush_wrap = wrap;
+                    }
+                }
+                "counter" => {
+                    if let Some(corner) = parse::<u32>(key, value) {
+                        uniforms.counter_corner = corner.min(4);
+                    }
+                }
+                "boundary" => {
+                    if let Some(mode) =
+                        parse::<u32>(key, value).filter(|mode| *mode <= BOUNDARY_REFLECT)
+                    {
+                        uniforms.boundary_mode = mode;
+                    }
+                }
+                "neighborhood" => {
+                    if let Some(neighborhood) = parse::<u32>(key, value)
+                        .filter(|neighborhood| *neighborhood <= NEIGHBORHOOD_VON_NEUMANN)
+                    {
+                        uniforms.neighborhood = neighborhood;
+                    }
+                }
+                "update" => {
+                    if let Some(scheme) =
+                        parse::<u32>(key, value).filter(|scheme| *scheme <= UPDATE_CHECKERBOARD)
+                    {
+                        uniforms.update_scheme = scheme;
+                    }
+                }
+                "zoom" => {
+                    if let Some(zoom) = parse::<f32>(key, value).filter(|z| z.is_finite()) {
+                        uniforms.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+                    }
+                }
+                "x" | "y" => {
+                    if let Some(pan) = parse::<f32>(key, value).filter(|p| p.is_finite()) {
+                        uniforms.pan[(key == "y") as usize] = pan;
+                    }
+                }
+                _ => warn!("Ignoring unknown setting {:?}", key),
+            }
+        }
+        uniforms.inner_radius = uniforms.inner_radius.min(uniforms.outer_radius);
+        self.clamp_pan(&mut uniforms);
+        drop(uniforms);
+        if normalize {
+            self.normalize_channels();
+        }
+    }
+
+    /// Rewrites every cell of every layer into the channels the current `sim_kind` uses, see
+    /// `fs_normalize`, so a populated board carries over into the new mode.
+    fn normalize_channels(&self) {
+        // The pass reads `sim_kind` from the uniform buffer
+        self.update();
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        for layer in &self.layers {
+            layer.encode_step(
+                &mut encoder,
+                &self.normalize_pipeline,
+                &self.uniforms_bind_group,
+                self.texture_size,
+                None,
+            );
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Uploads `palette` to the palette texture, an empty one goes back to the cells' own colors.
+    fn set_palette(&self, uniforms: &mut Uniforms, palette: Palette) {
+        if !palette.0.is_empty() {
+            self.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.palette_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(&palette.0),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(4 * palette.0.len() as u32),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: palette.0.len() as u32,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        uniforms.palette_len = palette.0.len() as u32;
+        *self.palette.write().unwrap() = palette;
+    }
+
+    /// Switches to the colors and gridlines of `preset`.
+    fn apply_palette_preset(&self, uniforms: &mut Uniforms, preset: PalettePreset) {
+        self.set_palette(uniforms, preset.palette());
+        (uniforms.minor_grid_color, uniforms.major_grid_color) = preset.grid_colors();
+        *self.palette_preset.write().unwrap() = preset;
+    }
+
+    fn set_paused(&self, paused: bool) {
+        *self.paused.write().unwrap() = paused;
+        *self.blur_paused.write().unwrap() = false;
+        *self.draw_paused.write().unwrap() = false;
+        warn!("{}", if paused { "Paused" } else { "Running" });
+    }
+
+    /// Advances one generation if paused, the running simulation ignores single steps.
+    fn single_step(self: &Arc<Self>) {
+        if *self.paused.read().unwrap() {
+            self.step();
+            self.maybe_inspect();
+        }
+    }
+
+    /// Refreshes the inspection tooltip for the hovered cell, at most every
+    /// `INSPECTION_INTERVAL_MS` and never with two readbacks in flight.
+    fn maybe_inspect(self: &Arc<Self>) {
+        if !*self.inspect.read().unwrap() || *self.inspecting.read().unwrap() {
+            return;
+        }
+        let now = web_sys::window().unwrap().performance().unwrap().now();
+        if now - *self.last_inspection.read().unwrap() < INSPECTION_INTERVAL_MS {
+            return;
+        }
+        let (Some(cell), Some(screen)) = (
+            *self.last_mousepos.read().unwrap(),
+            *self.last_screenpos.read().unwrap(),
+        ) else {
+            return;
+        };
+
+        *self.inspecting.write().unwrap() = true;
+        *self.last_inspection.write().unwrap() = now;
+        let state = Arc::clone(self);
+        wasm_bindgen_futures::spawn_local(async move {
+            let (alive, neighbors) = match state.inspect_cell(cell).await {
+                Ok(inspection) => inspection,
+                Err(e) => {
+                    warn!("Could not inspect the cell: {}", e);
+                    *state.inspecting.write().unwrap() = false;
+                    return;
+                }
+            };
+            state.tooltip.set_text_content(Some(&format!(
+                "({}, {}) {}, {} live neighbor{}",
+                cell.0,
+                cell.1,
+                if alive { "alive" } else { "dead" },
+                neighbors,
+                if neighbors == 1 { "" } else { "s" }
+            )));
+
+            let rect = state.canvas.get_bounding_client_rect();
+            let window = web_sys::window().unwrap();
+            let scale = rect.width() / state.canvas.width() as f64;
+            let style = state.tooltip.style();
+            let left = window.scroll_x().unwrap() + rect.left() + screen.0 as f64 * scale + 16.0;
+            let top = window.scroll_y().unwrap() + rect.top() + screen.1 as f64 * scale + 16.0;
+            style.set_property("left", &format!("{}px", left)).unwrap();
+            style.set_property("top", &format!("{}px", top)).unwrap();
+            state.tooltip.set_hidden(false);
+
+            *state.inspecting.write().unwrap() = false;
+        });
+    }
+
+    /// Shows `speed` over the top left corner of the canvas for `BADGE_MS`.
+    fn show_speed(self: &Arc<Self>, speed: u32) {
+        self.show_badge(&format!(
+            "{} generation{} per frame",
+            speed,
+            if speed == 1 { "" } else { "s" }
+        ));
+    }
+
+    /// Shows `text` over the top left corner of the canvas for `BADGE_MS`.
+    fn show_badge(self: &Arc<Self>, text: &str) {
+        let window = web_sys::window().unwrap();
+        let now = window.performance().unwrap().now();
+        *self.badge_until.write().unwrap() = now + BADGE_MS;
+
+        let rect = self.canvas.get_bounding_client_rect();
+        let style = self.badge.style();
+        let left = window.scroll_x().unwrap() + rect.left() + 8.0;
+        let top = window.scroll_y().unwrap() + rect.top() + 8.0;
+        style.set_property("left", &format!("{}px", left)).unwrap();
+        style.set_property("top", &format!("{}px", top)).unwrap();
+        self.badge.set_text_content(Some(text));
+        self.badge.set_hidden(false);
+
+        let state = Arc::downgrade(self);
+        wasm_bindgen_futures::spawn_local(async move {
+            sleep(BADGE_MS).await;
+            let Some(state) = state.upgrade() else {
+                return;
+            };
+            let now = web_sys::window().unwrap().performance().unwrap().now();
+            if now >= *state.badge_until.read().unwrap() {
+                state.badge.set_hidden(true);
+            }
+        });
+    }
+
+    /// Reads the 3x3 block around `cell` and returns whether the cell is alive and how many of
+    /// its neighbors are, finding the neighbors past the edges and leaving out the diagonal ones
+    /// the way the boundary mode and neighborhood of the shader do. Fails if the block can not be
+    /// read back.
+    async fn inspect_cell(&self, (x, y): (u32, u32)) -> Result<(bool, u32), String> {
+        let (width, height) = (self.texture_size.width, self.texture_size.height);
+        let x0 = x.saturating_sub(1);
+        let y0 = y.saturating_sub(1);
+        let x1 = (x + 1).min(width - 1);
+        let y1 = (y + 1).min(height - 1);
+        let block_width = x1 - x0 + 1;
+        let block = self.decode_texels(&self.read_region(x0, y0, block_width, y1 - y0 + 1).await?);
+        let texel_alive = |[r, g, b, _]: [f32; 4]| r * r + g * g + b * b > 0.5;
+
+        let uniforms = *self.uniforms.read().unwrap();
+        let boundary = match uniforms.boundary_mode {
+            BOUNDARY_TORUS => sim::Boundary::Torus,
+            BOUNDARY_DEAD => sim::Boundary::Dead,
+            _ => sim::Boundary::Reflect,
+        };
+        let mut alive = false;
+        let mut neighbors = 0;
+        for dy in -1i64..=1 {
+            for dx in -1i64..=1 {
+                let diagonal = dx != 0 && dy != 0;
+                if diagonal && uniforms.neighborhood != NEIGHBORHOOD_MOORE {
+                    continue;
+                }
+                let (Some(nx), Some(ny)) = (
+                    boundary.resolve(x as i64 + dx, width as i64),
+                    boundary.resolve(y as i64 + dy, height as i64),
+                ) else {
+                    continue;
+                };
+                let (nx, ny) = (nx as u32, ny as u32);
+                let cell_alive = if (x0..=x1).contains(&nx) && (y0..=y1).contains(&ny) {
+                    texel_alive(block[((ny - y0) * block_width + nx - x0) as usize])
+                } else {
+                    // Wrapped around to the opposite edge
+                    let texels = self.read_region(nx, ny, 1, 1).await?;
+                    texel_alive(self.decode_texels(&texels)[0])
+                };
+                if (dx, dy) == (0, 0) {
+                    alive = cell_alive;
+                } else if cell_alive {
+                    neighbors += 1;
+                }
+            }
+        }
+        Ok((alive, neighbors))
+    }
+
+    fn start_recording(&self) {
+        let now = web_sys::window().unwrap().performance().unwrap().now();
+        *self.recording.write().unwrap() = Some((now, Vec::new()));
+    }
+
+    /// Ends the recording and returns it as a log for `replay`.
+    fn stop_recording(&self) -> String {
+        self.recording
+            .write()
+            .unwrap()
+            .take()
+            .map(|(_, lines)| lines.join("\n"))
+            .unwrap_or_default()
+    }
+
+    /// Appends `event` to the log while recording, one line per event starting with the
+    /// milliseconds since the recording started. Pointer positions are stored as cells so a
+    /// log replays the same on a canvas of another size.
+    fn record(&self, event: &CanvasEvent) {
+        let mut recording = self.recording.write().unwrap();
+        let Some((start, lines)) = recording.as_mut() else {
+            return;
+        };
+        let line = match event {
+            CanvasEvent::MouseMove(x, y) => {
+                let (x, y) = self.screen_to_cell((*x, *y));
+                format!("move {} {}", x, y)
+            }
+            CanvasEvent::Wheel(delta) => format!("wheel {}", delta),
+            CanvasEvent::SpeedWheel(delta) => format!("speedwheel {}", delta),
+            CanvasEvent::MouseDown { shift, alt } => format!("down {} {}", shift, alt),
+            CanvasEvent::MouseUp => "up".to_owned(),
+            CanvasEvent::MouseLeave => "leave".to_owned(),
+            CanvasEvent::Play => "play".to_owned(),
+            CanvasEvent::Pause => "pause".to_owned(),
+            CanvasEvent::Step => "step".to_owned(),
+            CanvasEvent::Clear => "clear".to_owned(),
+            CanvasEvent::SaveSlot(slot) => format!("save {}", slot),
+            CanvasEvent::RestoreSlot(slot) => format!("restore {}", slot),
+            CanvasEvent::InjectNoise(flips) => format!("noise {}", flips),
+            CanvasEvent::ResetView => "home".to_owned(),
+            CanvasEvent::Randomize(gradient, None) => format!("randomize {}", gradient),
+            CanvasEvent::Randomize(gradient, Some(seed)) => {
+                format!("randomize {} {}", gradient, seed)
+            }
+            // Recording keys would restart or stop the recording during the replay
+            CanvasEvent::KeyDown { key, .. } if key == "r" || key == "R" => return,
+            CanvasEvent::KeyDown { key, ctrl } => format!("key {} {}", ctrl, key),
+            CanvasEvent::Visibility(..)
+            | CanvasEvent::Focus(..)
+            | CanvasEvent::LoadPattern(..)
+            | CanvasEvent::Resize
+            | CanvasEvent::ContextLost
+            | CanvasEvent::ContextRestored
+            | CanvasEvent::Reconfigure(..) => return,
+        };
+        let now = web_sys::window().unwrap().performance().unwrap().now();
+        lines.push(format!("{:.0} {}", now - *start, line));
+    }
+
+    /// Feeds the events of a log from `stop_recording` through `input` again at the pace they
+    /// were recorded. Lines that do not parse are skipped.
+    async fn replay(self: &Arc<Self>, log: &str) {
+        warn!("Replaying {} events", log.lines().count());
+        let performance = web_sys::window().unwrap().performance().unwrap();
+        let start = performance.now();
+        for line in log.lines() {
+            let Some((time, event)) = self.parse_recorded(line) else {
+                warn!("Skipping unreadable event {:?}", line);
+                continue;
+            };
+            sleep(time - (performance.now() - start)).await;
+            self.input(&event).await;
+            self.update();
+        }
+        warn!("Replay finished");
+    }
+
+    fn parse_recorded(&self, line: &str) -> Option<(f64, CanvasEvent)> {
+        let (time, rest) = line.split_once(' ')?;
+        let time = time.parse().ok()?;
+        let (kind, args) = rest.split_once(' ').unwrap_or((rest, ""));
+        let mut numbers = args.split(' ');
+        let event = match kind {
+            "move" => {
+                let x = numbers.next()?.parse().ok()?;
+                let y = numbers.next()?.parse().ok()?;
+                let (x, y) = self.cell_to_screen((x, y));
+                CanvasEvent::MouseMove(x, y)
+            }
+            "wheel" => CanvasEvent::Wheel(args.parse().ok()?),
+            "speedwheel" => CanvasEvent::SpeedWheel(args.parse().ok()?),
+            "down" => CanvasEvent::MouseDown {
+                shift: numbers.next()?.parse().ok()?,
+                alt: numbers.next()?.parse().ok()?,
+            },
+            "up" => CanvasEvent::MouseUp,
+            "leave" => CanvasEvent::MouseLeave,
+            "play" => CanvasEvent::Play,
+            "pause" => CanvasEvent::Pause,
+            "step" => CanvasEvent::Step,
+            "clear" => CanvasEvent::Clear,
+            "save" => CanvasEvent::SaveSlot(args.parse().ok()?),
+            "restore" => CanvasEvent::RestoreSlot(args.parse().ok()?),
+            "noise" => CanvasEvent::InjectNoise(args.parse().ok()?),
+            "home" => CanvasEvent::ResetView,
+            "randomize" => {
+                let (gradient, seed) = match args.split_once(' ') {
+                    Some((gradient, seed)) => (gradient, Some(seed.parse().ok()?)),
+                    None => (args, None),
+                };
+                CanvasEvent::Randomize(gradient.parse().ok()?, seed)
+            }
+            "key" => {
+                // The key is the rest of the line and may itself be a space
+                let (ctrl, key) = args.split_once(' ')?;
+                CanvasEvent::KeyDown {
+                    key: key.to_owned(),
+                    ctrl: ctrl.parse().ok()?,
+                }
+            }
+            _ => return None,
+        };
+        Some((time, event))
+    }
+
+    /// The canvas pixel at the center of `cell`, the inverse of `screen_to_cell`. Cells outside
+    /// the view map to the nearest canvas edge.
+    fn cell_to_screen(&self, (x, y): (u32, u32)) -> (u32, u32) {
+        let uniforms = self.uniforms.read().unwrap();
+        let view_x =
+            (x as f32 - uniforms.content_offset[0]).rem_euclid(self.texture_size.width as f32);
+        let view_y =
+            (y as f32 - uniforms.content_offset[1]).rem_euclid(self.texture_size.height as f32);
+        // The middle of cells in shifted rows is half a cell further right
+        let shift = if uniforms.brick == 1 && y % 2 == 1 {
+            0.5
+        } else {
+            0.0
+        };
+        let screen_x = (view_x + 0.5 + shift - uniforms.pan[0]) * uniforms.zoom;
+        let screen_y = (view_y + 0.5 - uniforms.pan[1]) * uniforms.zoom;
+        let screen_x = (screen_x.max(0.0) as u32).min(self.config.read().unwrap().width - 1);
+        let screen_y = (screen_y.max(0.0) as u32).min(self.config.read().unwrap().height - 1);
+        if *self.flip_y.read().unwrap() {
+            (screen_x, self.config.read().unwrap().height - 1 - screen_y)
+        } else {
+            (screen_x, screen_y)
+        }
+    }
+
+    /// The cell shown at canvas pixel `screen` with the current zoom and pan.
+    ///
+    /// Canvas pixels, the render target and the cell textures all have their origin in the
+    /// top-left corner with y pointing down, so the top-left canvas pixel is the top-left visible
+    /// cell, which is (0, 0) when not panned. `flip_y` mirrors the canvas y for setups where the
+    /// image ends up upside down relative to the pointer.
+    fn screen_to_cell(&self, (x, y): (u32, u32)) -> (u32, u32) {
+        let y = if *self.flip_y.read().unwrap() {
+            self.config.read().unwrap().height - 1 - y.min(self.config.read().unwrap().height - 1)
+        } else {
+            y
+        };
+        let uniforms = self.uniforms.read().unwrap();
+        let size = [
+            self.texture_size.width as f32,
+            self.texture_size.height as f32,
+        ];
+        let view = [
+            uniforms.pan[0] + x as f32 / uniforms.zoom,
+            uniforms.pan[1] + y as f32 / uniforms.zoom,
+        ];
+        // Like the shader: beside the board is background, and only the content offset of a
+        // scrolled wrapping board wraps around. The pointer beside the board is on the nearest
+        // cell of the edge rather than on the opposite one
+        let [cell_x, cell_y] = [0, 1].map(|axis| {
+            (view[axis].clamp(0.0, size[axis] - 0.5) + uniforms.content_offset[axis])
+                .rem_euclid(size[axis])
+        });
+        // Shifted rows show each cell half a cell further right, see `brick` in the shader
+        let beside = view[0] < 0.0 || view[0] >= size[0];
+        let cell_x = if uniforms.brick == 1 && cell_y as u32 % 2 == 1 && !beside {
+            (cell_x - 0.5).rem_euclid(size[0])
+        } else {
+            cell_x
+        };
+        (
+            (cell_x as u32).min(self.texture_size.width - 1),
+            (cell_y as u32).min(self.texture_size.height - 1),
+        )
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the cell under canvas pixel `screen` in place.
+    fn zoom_at(&self, (x, y): (u32, u32), factor: f32) {
+        // Moving the view by hand takes over from a running animation
+        *self.view_tween.write().unwrap() = None;
+        let mut uniforms = self.uniforms.write().unwrap();
+        let (x, y) = (x as f32, y as f32);
+        let anchor = [
+            uniforms.pan[0] + x / uniforms.zoom,
+            uniforms.pan[1] + y / uniforms.zoom,
+        ];
+        uniforms.zoom = (uniforms.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        uniforms.pan = [anchor[0] - x / uniforms.zoom, anchor[1] - y / uniforms.zoom];
+        self.scroll_pan(&mut uniforms);
+        self.clamp_pan(&mut uniforms);
+    }
+
+    /// Matches the canvas resolution to its size on the page and the device pixel ratio, keeping
+    /// the board, the zoom and the cell in the middle of the view.
+    fn fit_canvas(&self) {
+        let ratio = web_sys::window().unwrap().device_pixel_ratio();
+        let max = self.device.limits().max_texture_dimension_2d;
+        let width = ((self.canvas.client_width() as f64 * ratio).round() as u32).clamp(1, max);
+        let height = ((self.canvas.client_height() as f64 * ratio).round() as u32).clamp(1, max);
+        let mut config = self.config.write().unwrap();
+        if (width, height) == (config.width, config.height) {
+            return;
+        }
+
+        let mut uniforms = self.uniforms.write().unwrap();
+        let center = [
+            uniforms.pan[0] + config.width as f32 / uniforms.zoom / 2.0,
+            uniforms.pan[1] + config.height as f32 / uniforms.zoom / 2.0,
+        ];
+        self.canvas.set_width(width);
+        self.canvas.set_height(height);
+        config.width = width;
+        config.height = height;
+        self.surface.configure(&self.device, &config);
+        *self.msaa_view.write().unwrap() =
+            create_msaa_view(&self.device, &config, self.msaa_samples);
+        drop(config);
+
+        uniforms.canvas_size = [width as f32, height as f32];
+        uniforms.pan = [
+            center[0] - width as f32 / uniforms.zoom / 2.0,
+            center[1] - height as f32 / uniforms.zoom / 2.0,
+        ];
+        self.clamp_pan(&mut uniforms);
+        warn!("Canvas resized to {}x{}", width, height);
+    }
+
+    /// Moves the view by (`dx`, `dy`) canvas pixels.
+    fn pan_by(&self, dx: f32, dy: f32) {
+        *self.view_tween.write().unwrap() = None;
+        let mut uniforms = self.uniforms.write().unwrap();
+        uniforms.pan[0] += dx / uniforms.zoom;
+        uniforms.pan[1] += dy / uniforms.zoom;
+        self.scroll_pan(&mut uniforms);
+        self.clamp_pan(&mut uniforms);
+    }
+
+    /// Starts animating the view back to the whole board centered on the canvas, from whatever
+    /// zoom and pan it has now.
+    fn reset_view(&self) {
+        let size = [
+            self.texture_size.width as f32,
+            self.texture_size.height as f32,
+        ];
+        self.fit_view([0.0, 0.0], size, 0.0);
+    }
+
+    /// Starts animating the view to the `size` cells starting at `origin`, centered on the canvas
+    /// with `margin` of the canvas left free around them.
+    fn fit_view(&self, origin: [f32; 2], size: [f32; 2], margin: f32) {
+        let uniforms = self.uniforms.read().unwrap();
+        let config = self.config.read().unwrap();
+        let canvas = [config.width as f32, config.height as f32];
+        let board = [
+            self.texture_size.width as f32,
+            self.texture_size.height as f32,
+        ];
+        let to_zoom = ((canvas[0] / size[0].max(1.0)).min(canvas[1] / size[1].max(1.0))
+            * (1.0 - margin))
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        *self.view_tween.write().unwrap() = Some(ViewTween {
+            start: None,
+            from_zoom: uniforms.zoom,
+            from_center: [0, 1].map(|axis| uniforms.pan[axis] + canvas[axis] / uniforms.zoom / 2.0),
+            to_zoom,
+            // Where the cells are shown while scrolling, as close to the current view as fits
+            to_center: [0, 1].map(|axis| {
+                (origin[axis] + size[axis] / 2.0 - uniforms.content_offset[axis])
+                    .rem_euclid(board[axis])
+            }),
+        });
+    }
+
+    /// Moves the view along the running `view_tween` for the frame at `timestamp`.
+    fn advance_view_tween(&self, timestamp: f64) {
+        let mut view_tween = self.view_tween.write().unwrap();
+        let Some(tween) = view_tween.as_mut() else {
+            return;
+        };
+        let start = *tween.start.get_or_insert(timestamp);
+        let t = ((timestamp - start) / VIEW_TWEEN_MS).clamp(0.0, 1.0) as f32;
+        // Ease out, fast at first and settling gently
+        let t = 1.0 - (1.0 - t) * (1.0 - t);
+
+        let mut uniforms = self.uniforms.write().unwrap();
+        uniforms.zoom = tween.from_zoom * (tween.to_zoom / tween.from_zoom).powf(t);
+        let config = self.config.read().unwrap();
+        let canvas = [config.width as f32, config.height as f32];
+        for (axis, canvas_size) in canvas.into_iter().enumerate() {
+            let center =
+                tween.from_center[axis] + (tween.to_center[axis] - tween.from_center[axis]) * t;
+            uniforms.pan[axis] = center - canvas_size / uniforms.zoom / 2.0;
+        }
+        drop(config);
+        self.clamp_pan(&mut uniforms);
+        if t >= 1.0 {
+            *view_tween = None;
+        }
+    }
+
+    /// While scrolling on a wrapping board, moves the view back within the edges of the board and
+    /// the cells shown the other way around the torus by as much, so moving the view past an edge
+    /// keeps going instead of stopping there. The board itself stays where it is, a finite torus
+    /// that only shows up again after its full size, the offset is just added to every cell
+    /// shown or picked, which the rules do not care about since they are the same everywhere.
+    fn scroll_pan(&self, uniforms: &mut Uniforms) {
+        if uniforms.scrolling == 0 || uniforms.boundary_mode != BOUNDARY_TORUS {
+            return;
+        }
+        let visible = [
+            self.config.read().unwrap().width as f32 / uniforms.zoom,
+            self.config.read().unwrap().height as f32 / uniforms.zoom,
+        ];
+        let size = [
+            self.texture_size.width as f32,
+            self.texture_size.height as f32,
+        ];
+        for axis in 0..2 {
+            if visible[axis] >= size[axis] {
+                continue;
+            }
+            let pan = uniforms.pan[axis].clamp(0.0, size[axis] - visible[axis]);
+            uniforms.content_offset[axis] =
+                (uniforms.content_offset[axis] + uniforms.pan[axis] - pan).rem_euclid(size[axis]);
+            uniforms.pan[axis] = pan;
+        }
+    }
+
+    /// Keeps the view from showing anything beyond the edges of the board, or centers the board
+    /// when it is zoomed out to less than the canvas.
+    fn clamp_pan(&self, uniforms: &mut Uniforms) {
+        let visible = [
+            self.config.read().unwrap().width as f32 / uniforms.zoom,
+            self.config.read().unwrap().height as f32 / uniforms.zoom,
+        ];
+        let size = [
+            self.texture_size.width as f32,
+            self.texture_size.height as f32,
+        ];
+        for axis in 0..2 {
+            uniforms.pan[axis] = if visible[axis] >= size[axis] {
+                (size[axis] - visible[axis]) / 2.0
+            } else {
+                uniforms.pan[axis].clamp(0.0, size[axis] - visible[axis])
+            };
+        }
+    }
+
+    /// The rectangle spanned by the current drag, clamped to the texture.
+    fn drag_rect(&self) -> Option<Selection> {
+        let start = (*self.start_mousepos.read().unwrap())?;
+        let end = (*self.last_mousepos.read().unwrap())?;
+        let max_x = self.texture_size.width - 1;
+        let max_y = self.texture_size.height - 1;
+        Some((
+            start.0.min(end.0).min(max_x),
+            start.1.min(end.1).min(max_y),
+            start.0.max(end.0).min(max_x),
+            start.1.max(end.1).min(max_y),
+        ))
+    }
+
+    /// Kills every cell inside `selection` by drawing black into a scissored pass over both
+    /// simulation textures, so the board shows the region dead right away and the next compute
+    /// pass reads it as dead.
+    fn clear_region(&self, (x0, y0, x1, y1): Selection) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let layer = &self.layers[0];
+        for view in [&layer.texture_view, &layer.texture_target_view] {
+            let mut clear_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("clear pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            clear_pass.set_pipeline(&self.clear_pipeline);
+            clear_pass.set_scissor_rect(x0, y0, x1 - x0 + 1, y1 - y0 + 1);
+            clear_pass.draw(0..3, 0..1);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Turns the clipboard by 90° clockwise for `T`, mirrors it left to right for `X` and top to
+    /// bottom for `Y`, and outlines it at the cursor again with the corner the top-left cell of the
+    /// copied region ended up in marked.
+    fn transform_clipboard(&self, key: &str) {
+        let mut clipboard = self.clipboard.write().unwrap();
+        let Some((data, width, height)) = clipboard.as_mut() else {
+            return;
+        };
+        let texel = self.bytes_per_texel() as usize;
+        let mut uniforms = self.uniforms.write().unwrap();
+        // Corners are numbered clockwise from the top-left one
+        match key {
+            "T" => {
+                *data = transform::rotate_clockwise(data, *width, *height, texel);
+                std::mem::swap(width, height);
+                uniforms.paste_corner = (uniforms.paste_corner + 1) % 4;
+            }
+            "X" => {
+                *data = transform::flip_horizontal(data, *width, *height, texel);
+                uniforms.paste_corner ^= 1;
+            }
+            _ => {
+                *data = transform::flip_vertical(data, *width, *height, texel);
+                uniforms.paste_corner = 3 - uniforms.paste_corner;
+            }
+        }
+        uniforms.paste_size = [*width as f32, *height as f32];
+        self.announce(&format!("Holding {} by {} cells", width, height));
+    }
+
+    /// Starts recording the canvas into a WebM video for a number of seconds asked for, or stops
+    /// a recording early. Either way the video is downloaded once it ends.
+    ///
+    /// The browser encodes the frames as they are presented, so recording costs next to nothing
+    /// on top of drawing. Browsers without `MediaRecorder` only get a warning.
+    fn toggle_video(self: &Arc<Self>) {
+        if let Some(recorder) = self.video.write().unwrap().take() {
+            recorder.stop().unwrap();
+            return;
+        }
+        let window = web_sys::window().unwrap();
+        if !js_sys::Reflect::has(&window, &JsValue::from_str("MediaRecorder")).unwrap_or(false) {
+            warn!("This browser can not record videos, it lacks MediaRecorder");
+            return;
+        }
+        let answer = window.prompt_with_message_and_default(
+            "Seconds to record, press D again to stop early",
+            &self.video_seconds.to_string(),
+        );
+        let Ok(Some(answer)) = answer else {
+            return;
+        };
+        let seconds = match answer.trim().parse::<f64>() {
+            Ok(seconds) if seconds > 0.0 && seconds.is_finite() => seconds,
+            _ => {
+                warn!("Expected a positive number of seconds, got {:?}", answer);
+                return;
+            }
+        };
+
+        // `captureStream` is missing from web-sys
+        let recorder = js_sys::Reflect::get(&self.canvas, &JsValue::from_str("captureStream"))
+            .and_then(|capture| capture.dyn_into::<js_sys::Function>())
+            .and_then(|capture| capture.call1(&self.canvas, &JsValue::from(VIDEO_FRAME_RATE)))
+            .and_then(|stream| {
+                let stream = stream.unchecked_into::<web_sys::MediaStream>();
+                web_sys::MediaRecorder::new_with_media_stream_and_media_recorder_options(
+                    &stream,
+                    web_sys::MediaRecorderOptions::new().mime_type("video/webm"),
+                )
+            });
+        let recorder = match recorder {
+            Ok(recorder) => recorder,
+            Err(e) => {
+                warn!("Could not record the canvas: {:?}", e);
+                return;
+            }
+        };
+        let chunks = js_sys::Array::new();
+        let ondataavailable = {
+            let chunks = chunks.clone();
+            Closure::<dyn FnMut(_)>::new(move |event: web_sys::BlobEvent| {
+                if let Some(data) = event.data() {
+                    chunks.push(&data);
+                }
+            })
+        };
+        let onstop = Closure::<dyn FnMut()>::new(move || {
+            let video = web_sys::Blob::new_with_blob_sequence_and_options(
+                &chunks,
+                web_sys::BlobPropertyBag::new().type_("video/webm"),
+            )
+            .unwrap();
+            download_blob(&video, "life.webm");
+            warn!("Stopped recording video");
+        });
+        // Handed over to the recorder, so they are freed along with it
+        recorder.set_ondataavailable(Some(ondataavailable.into_js_value().unchecked_ref()));
+        recorder.set_onstop(Some(onstop.into_js_value().unchecked_ref()));
+        if let Err(e) = recorder.start() {
+            warn!("Could not record the canvas: {:?}", e);
+            return;
+        }
+        warn!("Recording video for {} s", seconds);
+        *self.video.write().unwrap() = Some(recorder.clone());
+
+        let state = Arc::clone(self);
+        wasm_bindgen_futures::spawn_local(async move {
+            sleep(seconds * 1000.0).await;
+            let mut video = state.video.write().unwrap();
+            // Unless it was stopped early
+            if video.as_ref() == Some(&recorder) {
+                *video = None;
+                recorder.stop().unwrap();
+            }
+        });
+    }
+
+    /// Stamps the clipboard with its top-left corner at `origin`, clipping whatever would fall
+    /// outside the texture instead of wrapping it around.
+    fn paste(&self, (x, y): (u32, u32)) {
+        let clipboard = self.clipboard.read().unwrap();
+        let Some((data, width, height)) = clipboard.as_ref() else {
+            return;
+        };
+        if x >= self.texture_size.width || y >= self.texture_size.height {
+            return;
+        }
+
+        self.write_region(
+            (x, y),
+            (
+                (*width).min(self.texture_size.width - x),
+                (*height).min(self.texture_size.height - y),
+            ),
+            *width,
+            data,
+        );
+    }
+
+    /// Writes `data`, rows of `stride` texels, into the block of `size` at `origin` of both
+    /// textures of the first layer, so the board shows it right away and the next generation
+    /// starts from it.
+    fn write_region(&self, origin: (u32, u32), size: (u32, u32), stride: u32, data: &[u8]) {
+        for texture in [&self.layers[0].texture, &self.layers[0].texture_target] {
+            self.queue.write_texture(
+                wgpu::ImageCopyTextureBase {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: origin.0,
+                        y: origin.1,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(stride * self.bytes_per_texel()),
+                    rows_per_image: std::num::NonZeroU32::new(size.1),
+                },
+                wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    fn bytes_per_texel(&self) -> u32 {
+        self.cell_format.describe().block_size as u32
+    }
+
+    /// Converts raw texels in the cell format to RGBA.
+    fn decode_texels(&self, bytes: &[u8]) -> Vec<[f32; 4]> {
+        match self.cell_format {
+            wgpu::TextureFormat::Rgba16Float => bytemuck::pod_collect_to_vec::<u8, u16>(bytes)
+                .chunks(4)
+                .map(|c| [c[0], c[1], c[2], c[3]].map(|c| half::f16::from_bits(c).to_f32()))
+                .collect(),
+            _ => bytemuck::pod_collect_to_vec(bytes),
+        }
+    }
+
+    /// Converts RGBA texels into raw bytes in the cell format.
+    fn encode_texels(&self, texels: &[[f32; 4]]) -> Vec<u8> {
+        match self.cell_format {
+            wgpu::TextureFormat::Rgba16Float => texels
+                .iter()
+                .flat_map(|texel| texel.map(|c| half::f16::from_f32(c).to_bits()))
+                .flat_map(u16::to_ne_bytes)
+                .collect(),
+            _ => bytemuck::cast_slice(texels).to_vec(),
+        }
+    }
+
+    /// Writes `pattern` with its top-left corner at (`x`, `y`) into the board, dropping the parts
+    /// that fall outside the texture.
+    fn stamp(&self, pattern: &Pattern, x: i32, y: i32) {
+        self.extinct_message.set_hidden(true);
+        let (width, height) = (
+            self.texture_size.width as i32,
+            self.texture_size.height as i32,
+        );
+        let x0 = x.max(0);
+        let y0 = y.max(0);
+        let x1 = (x + pattern.width as i32).min(width);
+        let y1 = (y + pattern.height as i32).min(height);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        let texels: Vec<[f32; 4]> = (y0..y1)
+            .flat_map(|cy| (x0..x1).map(move |cx| (cx, cy)))
+            .map(|(cx, cy)| {
+                if pattern.get((cx - x) as u32, (cy - y) as u32) {
+                    PATTERN_COLOR
+                } else {
+                    [0.0, 0.0, 0.0, 1.0]
+                }
+            })
+            .collect();
+        let size = ((x1 - x0) as u32, (y1 - y0) as u32);
+        self.write_region(
+            (x0 as u32, y0 as u32),
+            size,
+            size.0,
+            &self.encode_texels(&texels),
+        );
+    }
+
+    /// Flips the cell at (`x`, `y`) between alive and dead.
+    ///
+    /// The new texel is written to both textures so the readback of the next toggle sees it even
+    /// if no generation ran in between. Events are handled one at a time, so a burst of clicks
+    /// never overlaps two readbacks. Fails, leaving the cell alone, if it can not be read back.
+    /// Returns whether the cell is alive afterwards.
+    async fn toggle_cell(&self, x: u32, y: u32) -> Result<bool, String> {
+        if x >= self.texture_size.width || y >= self.texture_size.height {
+            return Ok(false);
+        }
+        let [r, g, b, _] = self.decode_texels(&self.read_region(x, y, 1, 1).await?)[0];
+        let alive = r * r + g * g + b * b <= 0.5;
+        if alive {
+            self.extinct_message.set_hidden(true);
+        }
+        let texel = if alive {
+            PATTERN_COLOR
+        } else {
+            [0.0, 0.0, 0.0, 1.0]
+        };
+
+        self.write_cell(x, y, texel);
+        Ok(alive)
+    }
+
+    /// Writes `texel` into the cell at (`x`, `y`) of both textures of the first layer.
+    fn write_cell(&self, x: u32, y: u32, texel: [f32; 4]) {
+        self.write_region((x, y), (1, 1), 1, &self.encode_texels(&[texel]));
+    }
+
+    /// Brings every cell of the straight line from `from` to `to` to life, the same cells the
+    /// preview from the anchor showed.
+    fn draw_line(&self, from: (u32, u32), to: (u32, u32)) {
+        self.extinct_message.set_hidden(true);
+        let cells = line_cells(from, to);
+        for &(x, y) in &cells {
+            self.write_cell(x, y, PATTERN_COLOR);
+        }
+        warn!(
+            "Drew a line of {} cells from {}, {} to {}, {}",
+            cells.len(),
+            from.0,
+            from.1,
+            to.0,
+            to.1
+        );
+    }
+
+    /// Flips `flips` random cells inside the active region to probe how robust the board is. The
+    /// cells only depend on the generation, so replaying the same board flips the same cells.
+    /// The region is read back once and written back once, a board that can not be read back is
+    /// left alone.
+    async fn inject_noise(&self, flips: u32) {
+        let generation = *self.generation.read().unwrap();
+        let mut rng = Rng(generation ^ NOISE_SEED);
+        let [x0, y0, x1, y1] = self
+            .uniforms
+            .read()
+            .unwrap()
+            .active_region
+            .map(|c| c as u32);
+        let (width, height) = (x1 - x0 + 1, y1 - y0 + 1);
+        let mut texels = match self.read_region(x0, y0, width, height).await {
+            Ok(data) => self.decode_texels(&data),
+            Err(e) => {
+                warn!("Could not inject noise: {}", e);
+                return;
+            }
+        };
+        for _ in 0..flips {
+            let x = rng.next_u64() % width as u64;
+            let y = rng.next_u64() % height as u64;
+            let texel = &mut texels[(y * width as u64 + x) as usize];
+            let [r, g, b, _] = *texel;
+            *texel = if r * r + g * g + b * b > 0.5 {
+                [0.0, 0.0, 0.0, 1.0]
+            } else {
+                PATTERN_COLOR
+            };
+        }
+        self.write_region(
+            (x0, y0),
+            (width, height),
+            width,
+            &self.encode_texels(&texels),
+        );
+        warn!(
+            "Flipped {} random cells at generation {}",
+            flips, generation
+        );
+    }
+
+    /// Moves the keyboard edit cursor by one cell, placing it in the middle of the view first if
+    /// it is not shown yet, and scrolls the view to keep it visible.
+    fn move_edit_cursor(&self, dx: i32, dy: i32) {
+        let mut uniforms = self.uniforms.write().unwrap();
+        let visible = [
+            self.config.read().unwrap().width as f32 / uniforms.zoom,
+            self.config.read().unwrap().height as f32 / uniforms.zoom,
+        ];
+        let (x, y) = match *self.edit_cursor.read().unwrap() {
+            Some((x, y)) => (
+                (x as i32 + dx).clamp(0, self.texture_size.width as i32 - 1) as u32,
+                (y as i32 + dy).clamp(0, self.texture_size.height as i32 - 1) as u32,
+            ),
+            None => (
+                (uniforms.pan[0] + visible[0] / 2.0) as u32,
+                (uniforms.pan[1] + visible[1] / 2.0) as u32,
+            ),
+        };
+        *self.edit_cursor.write().unwrap() = Some((x, y));
+
+        for (axis, cell) in [x, y].into_iter().enumerate() {
+            let cell = cell as f32;
+            if cell < uniforms.pan[axis] {
+                uniforms.pan[axis] = cell;
+            } else if cell + 1.0 > uniforms.pan[axis] + visible[axis] {
+                uniforms.pan[axis] = cell + 1.0 - visible[axis];
+            }
+        }
+        self.clamp_pan(&mut uniforms);
+        drop(uniforms);
+
+        self.announce(&format!("Cursor at {}, {}", x, y));
+    }
+
+    /// Tells screen readers about `message` through the live region next to the canvas.
+    fn announce(&self, message: &str) {
+        self.live_region.set_text_content(Some(message));
+    }
+
+    /// Reads back a `width` x `height` block of texels starting at (`x`, `y`) as tightly packed
+    /// rows in the cell format. Fails if the texels can not be mapped, e.g. after the device was
+    /// lost.
+    async fn read_region(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, String> {
+        let bytes_per_pixel = self.bytes_per_texel();
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTextureBase {
+                texture: &self.layers[0].texture_target,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBufferBase {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .await
+            .map_err(|_| "The readback was dropped".to_string())?
+            .map_err(|e| format!("Could not read the board back: {}", e))?;
+
+        let data = slice
+            .get_mapped_range()
+            .chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect();
+        buffer.unmap();
+        Ok(data)
+    }
+
+    /// Records one generation of every active layer.
+    fn encode_step(&self, encoder: &mut wgpu::CommandEncoder) {
+        *self.generation.write().unwrap() += 1;
+        let mut uniforms = self.uniforms.write().unwrap();
+        if uniforms.update_scheme == UPDATE_CHECKERBOARD {
+            uniforms.phase = (*self.generation.read().unwrap() % 2) as u32;
+            encoder.copy_buffer_to_buffer(
+                &self.phase_buffer,
+                uniforms.phase as u64 * 4,
+                &self.uniforms_buffer,
+                std::mem::offset_of!(Uniforms, phase) as u64,
+                4,
+            );
+        }
+        drop(uniforms);
+        let active = if *self.dual_layer.read().unwrap() {
+            2
+        } else {
+            1
+        };
+        for layer in &self.layers[..active] {
+            layer.encode_step(
+                encoder,
+                &self.compute_pipeline.read().unwrap(),
+                &self.uniforms_bind_group,
+                self.texture_size,
+                None,
+            );
+        }
+    }
+
+    /// Copies the first layer into `previous_texture` for the crossfade to the next generation
+    /// or to show which cells changed.
+    fn save_previous(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_texture(
+            wgpu::ImageCopyTextureBase {
+                texture: &self.layers[0].texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::default(),
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyTextureBase {
+                texture: &self.previous_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::default(),
+                aspect: wgpu::TextureAspect::All,
+            },
+            self.texture_size,
+        );
+    }
+
+    /// Advances only the cells inside the selection by one generation, the rest of the board
+    /// stays frozen and the generation counter is left alone.
+    fn step_selection(&self) {
+        let Some(selection) = *self.selection.read().unwrap() else {
+            return;
+        };
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let active = if *self.dual_layer.read().unwrap() {
+            2
+        } else {
+            1
+        };
+        for layer in &self.layers[..active] {
+            layer.encode_step(
+                &mut encoder,
+                &self.compute_pipeline.read().unwrap(),
+                &self.uniforms_bind_group,
+                self.texture_size,
+                Some(selection),
+            );
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Records the brush stroke into the newest generation, so drawn cells are shown as drawn and
+    /// only evaluated by the rule from the next generation on.
+    fn encode_draw(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut uniforms = self.uniforms.write().unwrap();
+        if uniforms.mouse_pos[0] < 0.0 {
+            return;
+        }
+        if uniforms.brush_density < 1.0 {
+            // A new pick of cells every frame, so holding the button still sprays more
+            uniforms.frame = uniforms.frame.wrapping_add(1);
+            self.queue
+                .write_buffer(&self.uniforms_buffer, 0, bytemuck::cast_slice(&[*uniforms]));
+        }
+        drop(uniforms);
+        let layer = &self.layers[0];
+        {
+            let mut draw_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("draw pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &layer.texture_target_view,
+                    resolve_target: None,
+                    // Everything outside the brush is discarded and keeps its cell
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            draw_pass.set_pipeline(&self.draw_pipeline);
+            draw_pass.set_bind_group(0, &self.empty_bind_group, &[]);
+            draw_pass.set_bind_group(1, &self.uniforms_bind_group, &[]);
+            draw_pass.draw(0..3, 0..1);
+        }
+        encoder.copy_texture_to_texture(
+            wgpu::ImageCopyTextureBase {
+                texture: &layer.texture_target,
+                mip_level: 0,
+                origin: wgpu::Origin3d::default(),
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyTextureBase {
+                texture: &layer.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::default(),
+                aspect: wgpu::TextureAspect::All,
+            },
+            self.texture_size,
+        );
+    }
+
+    /// Rebuilds the mip chain of the display copy, only while zoomed out far enough to sample it,
+    /// or just its first level while smooth zoom magnifies it.
+    fn encode_display_mips(&self, encoder: &mut wgpu::CommandEncoder) {
+        let uniforms = *self.uniforms.read().unwrap();
+        if uniforms.layers != 1 {
+            return;
+        }
+        let mut levels = if uniforms.filtered_zoom == 1 && uniforms.zoom < 1.0 {
+            DISPLAY_MIP_LEVELS as usize
+        } else if uniforms.smooth_zoom == 1 && uniforms.zoom > 1.0 {
+            1
+        } else {
+            0
+        };
+        if uniforms.glow == 1 {
+            levels = levels.max(GLOW_LEVEL + 1);
+        }
+
+        for (level, level_view) in self.display_mips.level_views[..levels].iter().enumerate() {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("mip pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: level_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            if level == 0 {
+                pass.set_pipeline(&self.mip_base_pipeline);
+                pass.set_bind_group(0, &self.layers[0].texture_bind_group, &[]);
+            } else {
+                pass.set_pipeline(&self.downsample_pipeline);
+                pass.set_bind_group(0, &self.display_mips.level_bind_groups[level - 1], &[]);
+            }
+            pass.draw(0..3, 0..1);
+        }
+        if uniforms.glow == 1 {
+            self.encode_glow(encoder);
+        }
+    }
+
+    /// Blurs `GLOW_LEVEL` of the display copy, which must be up to date, into the glow.
+    fn encode_glow(&self, encoder: &mut wgpu::CommandEncoder) {
+        let passes = [
+            (
+                &self.glow_horizontal_pipeline,
+                &self.glow.horizontal_bind_group,
+                &self.glow.horizontal_view,
+            ),
+            (
+                &self.glow_vertical_pipeline,
+                &self.glow.vertical_bind_group,
+                &self.glow.view,
+            ),
+        ];
+        for (pipeline, bind_group, target) in passes {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("glow pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_bind_group(1, &self.uniforms_bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+
+    /// Advances the simulation by one generation without presenting anything.
+    fn step(&self) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        if self.uniforms.read().unwrap().show_changes == 1 {
+            self.save_previous(&mut encoder);
+        }
+        self.encode_step(&mut encoder);
+        self.apply_scheduled_stamps(&mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Stamps `pattern` centered on cell (`x`, `y`) every `interval` generations from now on.
+    fn schedule_stamp(&self, pattern: Pattern, x: u32, y: u32, interval: u64) {
+        let stamp = ScheduledStamp {
+            x: x as i32 - pattern.width as i32 / 2,
+            y: y as i32 - pattern.height as i32 / 2,
+            pattern,
+            interval: interval.max(1),
+            start: *self.generation.read().unwrap(),
+        };
+        let mut stamps = self.scheduled_stamps.write().unwrap();
+        stamps.push(stamp);
+        warn!(
+            "Stamping at {}, {} every {} generations, {} scheduled, Q clears them",
+            x,
+            y,
+            interval,
+            stamps.len()
+        );
+    }
+
+    /// Applies the scheduled stamps due at the generation `encoder` just computed.
+    fn apply_scheduled_stamps(&self, encoder: &mut wgpu::CommandEncoder) {
+        let generation = *self.generation.read().unwrap();
+        let stamps = self.scheduled_stamps.read().unwrap();
+        let mut due = stamps
+            .iter()
+            .filter(|stamp| {
+                generation > stamp.start
+                    && (generation - stamp.start).is_multiple_of(stamp.interval)
+            })
+            .peekable();
+        if due.peek().is_none() {
+            return;
+        }
+        // Stamps are written through the queue, which would run them before the generations
+        // still waiting in the encoder
+        let encoded = std::mem::replace(
+            encoder,
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None }),
+        );
+        self.queue.submit(std::iter::once(encoded.finish()));
+        for stamp in due {
+            self.stamp(&stamp.pattern, stamp.x, stamp.y);
+        }
+    }
+
+    /// Asks for a number of generations and advances that many, up to `MAX_JUMP`, without
+    /// drawing any but the last. Progress is shown for jumps of more than one batch. Like running,
+    /// it stops at `data-max-generations`.
+    fn jump_prompt(self: &Arc<Self>) {
+        if *self.jumping.read().unwrap() {
+            warn!("Still jumping ahead");
+            return;
+        }
+        let answer = web_sys::window()
+            .unwrap()
+            .prompt_with_message_and_default("Generations to advance", "1000");
+        let Ok(Some(answer)) = answer else {
+            return;
+        };
+        let generations = match answer.trim().parse::<u64>() {
+            Ok(generations) if generations > MAX_JUMP => {
+                warn!(
+                    "Capping the jump of {} generations to {}",
+                    generations, MAX_JUMP
+                );
+                MAX_JUMP
+            }
+            Ok(generations) => generations,
+            Err(_) => {
+                warn!("Expected a number of generations, got {:?}", answer);
+                return;
+            }
+        };
+        let generations = self.limit_steps(generations);
+        if generations == 0 {
+            return;
+        }
+        // The crossfade and the changed cells show the last of the generations
+        let save_previous =
+            *self.interpolate.read().unwrap() || self.uniforms.read().unwrap().show_changes == 1;
+
+        *self.jumping.write().unwrap() = true;
+        let state = Arc::clone(self);
+        wasm_bindgen_futures::spawn_local(async move {
+            let mut done = 0;
+            while done < generations {
+                let batch = (generations - done).min(JUMP_BATCH);
+                let mut encoder = state
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                for i in 0..batch {
+                    if save_previous && done + i + 1 == generations {
+                        state.save_previous(&mut encoder);
+                    }
+                    state.encode_step(&mut encoder);
+                    state.apply_scheduled_stamps(&mut encoder);
+                }
+                state.queue.submit(std::iter::once(encoder.finish()));
+                done += batch;
+                if generations > JUMP_BATCH {
+                    state.show_badge(&format!("{} of {} generations", done, generations));
+                }
+                sleep(0.0).await;
+            }
+            *state.jumping.write().unwrap() = false;
+            let message = format!(
+                "Advanced {} generations, now at generation {}",
+                generations,
+                state.generation.read().unwrap()
+            );
+            warn!("{}", message);
+            state.announce(&message);
+        });
+    }
+
+    /// Advances the freshly seeded or loaded board by the configured number of warmup
+    /// generations without presenting any of them.
+    fn warm_up(&self) {
+        if self.warmup == 0 {
+            return;
+        }
+        let mut remaining = self.warmup;
+        while remaining > 0 {
+            let batch = remaining.min(100);
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            for _ in 0..batch {
+                self.encode_step(&mut encoder);
+            }
+            self.queue.submit(std::iter::once(encoder.finish()));
+            remaining -= batch;
+        }
+        warn!(
+            "Warmed up {} generations, now at generation {}",
+            self.warmup,
+            self.generation.read().unwrap()
+        );
+    }
+
+    /// Steps `frames * step_per_frame` generations while the live simulation is paused, grabbing
+    /// every `step_per_frame`th generation as a frame of an animated GIF. The brush is kept out
+    /// of it, and boards too large for a GIF are an error.
+    async fn export_gif(&self, frames: u32, step_per_frame: u32) -> Result<Vec<u8>, String> {
+        let (Ok(width), Ok(height)) = (
+            u16::try_from(self.texture_size.width),
+            u16::try_from(self.texture_size.height),
+        ) else {
+            return Err(format!(
+                "GIFs can be at most {} pixels across, the board is {}x{}",
+                u16::MAX,
+                self.texture_size.width,
+                self.texture_size.height
+            ));
+        };
+        let was_paused = *self.paused.read().unwrap();
+        *self.paused.write().unwrap() = true;
+        *self.capturing_gif.write().unwrap() = true;
+        self.update();
+
+        let gif = self.encode_gif(width, height, frames, step_per_frame).await;
+
+        *self.capturing_gif.write().unwrap() = false;
+        *self.paused.write().unwrap() = was_paused;
+        gif
+    }
+
+    /// Encodes the frames of `export_gif`, advancing the board between them.
+    async fn encode_gif(
+        &self,
+        width: u16,
+        height: u16,
+        frames: u32,
+        step_per_frame: u32,
+    ) -> Result<Vec<u8>, String> {
+        let encoding_error = |e: gif::EncodingError| format!("Could not encode the GIF: {}", e);
+        let mut gif = Vec::new();
+        {
+            let mut encoder =
+                gif::Encoder::new(&mut gif, width, height, &[]).map_err(encoding_error)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(encoding_error)?;
+
+            for frame in 0..frames {
+                for _ in 0..step_per_frame {
+                    self.step();
+                }
+
+                let texels = self.read_region(0, 0, width as u32, height as u32).await?;
+                let mut pixels: Vec<u8> = self
+                    .decode_texels(&texels)
+                    .into_iter()
+                    .flat_map(|texel| {
+                        let [r, g, b, _] = texel.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+                        [r, g, b, 255]
+                    })
+                    .collect();
+
+                let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 30);
+                gif_frame.delay = 10;
+                encoder.write_frame(&gif_frame).map_err(encoding_error)?;
+                warn!("Captured GIF frame {}/{}", frame + 1, frames);
+            }
+        }
+        Ok(gif)
+    }
+
+    /// Clears the board and fills a `SOUP_SIZE` square in its center, shrunk to fit boards smaller
+    /// than that, with a random soup of 50% density that is fully determined by `seed`.
+    fn fill_soup(&self, seed: u64) {
+        let width = self.texture_size.width;
+        let height = self.texture_size.height;
+        self.clear_region((0, 0, width - 1, height - 1));
+        let size = SOUP_SIZE.min(width).min(height);
+
+        let mut rng = Rng(seed);
+        let color = [rng.next_f32(), rng.next_f32(), rng.next_f32()];
+        let length = color
+            .iter()
+            .map(|c| c * c)
+            .sum::<f32>()
+            .sqrt()
+            .max(f32::EPSILON);
+        let alive = [color[0] / length, color[1] / length, color[2] / length, 1.0];
+        let dead = [0.0, 0.0, 0.0, 1.0];
+
+        let texels: Vec<[f32; 4]> = (0..size * size)
+            .map(|_| if rng.next_u64() & 1 == 1 { alive } else { dead })
+            .collect();
+        self.write_region(
+            ((width - size) / 2, (height - size) / 2),
+            (size, size),
+            size,
+            &self.encode_texels(&texels),
+        );
+    }
+
+    /// Replaces the whole board with random cells that are alive with probability `density`.
+    fn randomize(&self, gradient: Gradient, seed: u64) {
+        self.extinct_message.set_hidden(true);
+        let mut rng = Rng(seed);
+        let (width, height) = (self.texture_size.width, self.texture_size.height);
+        let texels: Vec<[f32; 4]> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                // Cell centers in [0, 1]
+                let u = (x as f32 + 0.5) / width as f32;
+                let v = (y as f32 + 0.5) / height as f32;
+                if rng.next_f32() < gradient.density(u, v) {
+                    PATTERN_COLOR
+                } else {
+                    [0.0, 0.0, 0.0, 1.0]
+                }
+            })
+            .collect();
+        let (width, height) = (self.texture_size.width, self.texture_size.height);
+        self.write_region((0, 0), (width, height), width, &self.encode_texels(&texels));
+    }
+
+    /// The live cells of the most recent generation, the size of the whole board.
+    async fn board(&self) -> Result<Pattern, String> {
+        let (width, height) = (self.texture_size.width, self.texture_size.height);
+        let texels = self.read_region(0, 0, width, height).await?;
+        let cells = self
+            .decode_texels(&texels)
+            .into_iter()
+            .map(|[r, g, b, _]| r * r + g * g + b * b > 0.5)
+            .collect();
+        Ok(Pattern {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    /// The live cells of the most recent generation, trimmed to their bounding box.
+    async fn board_pattern(&self) -> Result<Pattern, String> {
+        Ok(self.board().await?.trimmed())
+    }
+
+    /// After how many generations the board repeats, see `Simulation::period`.
+    async fn period(&self) -> Result<u32, String> {
+        let rule = self.cpu_rule()?;
+        let (_, region) = self.live_region(&self.board().await?);
+        Ok(region.period(rule, MAX_PERIOD))
+    }
+
+    /// The rule of the first layer for running it on the CPU, for the B/S rules with synchronous
+    /// updates only. Past the edges is dead, see `live_region`.
+    fn cpu_rule(&self) -> Result<sim::Rule, String> {
+        let uniforms = *self.uniforms.read().unwrap();
+        if uniforms.sim_kind != SimKind::Classic as u32
+            || uniforms.neighbor_radius > 1
+            || uniforms.update_scheme != UPDATE_SYNCHRONOUS
+        {
+            return Err(
+                "Repetition is only detected under B/S rules with synchronous updates".into(),
+            );
+        }
+        let layer_rule = *self.layers[0].rule.read().unwrap();
+        Ok(sim::Rule {
+            birth: layer_rule.birth,
+            survive: layer_rule.survive,
+            boundary: sim::Boundary::Dead,
+            neighborhood: match uniforms.neighborhood {
+                NEIGHBORHOOD_MOORE => sim::Neighborhood::Moore,
+                _ => sim::Neighborhood::VonNeumann,
+            },
+            kill_margin: 0,
+        })
+    }
+
+    /// The live cells of `board` for running them on the CPU, surrounded by enough dead cells
+    /// that nothing they do within `MAX_PERIOD` generations reaches the edges, and the cell of
+    /// `board` its top-left corner is on. The edges of the board are only taken into account as
+    /// far as the cells are from them.
+    fn live_region(&self, board: &Pattern) -> ((i64, i64), sim::Board) {
+        let margin = (MAX_PERIOD + 1) as i64;
+        let (x0, y0, x1, y1) = board.bounds().unwrap_or((0, 0, 0, 0));
+        let origin = (x0 as i64 - margin, y0 as i64 - margin);
+        let mut region = sim::Board::new(
+            x1 - x0 + 1 + 2 * margin as u32,
+            y1 - y0 + 1 + 2 * margin as u32,
+        );
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                if board.get(x, y) {
+                    region.set(
+                        (x as i64 - origin.0) as u32,
+                        (y as i64 - origin.1) as u32,
+                        true,
+                    );
+                }
+            }
+        }
+        (origin, region)
+    }
+
+    /// Tints the still lifes and the oscillators on a board that has settled into repeating
+    /// itself and logs how many there are of each, or hides the tints again.
+    async fn classify(&self) {
+        if self.uniforms.read().unwrap().classified == 1 {
+            self.uniforms.write().unwrap().classified = 0;
+            return;
+        }
+        let rule = match self.cpu_rule() {
+            Ok(rule) => rule,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        let board = match self.board().await {
+            Ok(board) => board,
+            Err(e) => {
+                warn!("Could not classify the board: {}", e);
+                return;
+            }
+        };
+        if board.bounds().is_none() {
+            warn!("Nothing to classify on an empty board");
+            return;
+        }
+        let (origin, region) = self.live_region(&board);
+        let period = region.period(rule, MAX_PERIOD);
+        if period == 0 {
+            warn!("The board has not settled into still lifes and oscillators yet");
+            return;
+        }
+
+        let (width, height) = (self.texture_size.width, self.texture_size.height);
+        let mut classes = vec![0; (width * height) as usize];
+        let (mut still_lifes, mut oscillators) = (0, 0);
+        for component in region.components(rule, period) {
+            let class = match component.kind {
+                sim::Kind::StillLife => {
+                    still_lifes += 1;
+                    CLASS_STILL_LIFE
+                }
+                sim::Kind::Oscillator => {
+                    oscillators += 1;
+                    CLASS_OSCILLATOR
+                }
+            };
+            for (x, y) in component.cells {
+                let (x, y) = (x as i64 + origin.0, y as i64 + origin.1);
+                if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                    classes[(y * width as i64 + x) as usize] = class;
+                }
+            }
+        }
+        self.queue.write_texture(
+            wgpu::ImageCopyTextureBase {
+                texture: &self.classes_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::default(),
+                aspect: wgpu::TextureAspect::All,
+            },
+            &classes,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(width),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+            self.texture_size,
+        );
+        self.uniforms.write().unwrap().classified = 1;
+        let message = format!(
+            "{} still lifes and {} oscillators, repeating every {} generations",
+            still_lifes, oscillators, period
+        );
+        warn!("{}", message);
+        self.announce(&message);
+    }
+
+    /// A state for the same canvas with a board of another size, with the settings, the custom
+    /// rule, the generation and the board carried over. `self` is left untouched and keeps being
+    /// drawn until the caller swaps the result in, so no frame ever shows a half built board.
+    async fn reconfigured(&self, grid: GridConfig) -> Result<Self, InitError> {
+        let board = self.board().await.map_err(InitError::Readback)?;
+        let mut settings = Settings::from_canvas(&self.canvas);
+        settings.board_width = grid.width;
+        settings.board_height = grid.height;
+        settings.rule_wgsl = self.custom_rule.read().unwrap().clone();
+        settings.initial_fill = None;
+        settings.warmup = 0;
+        let new = State::new(&self.canvas, settings).await?;
+
+        new.apply_settings(&self.serialize_settings());
+        *new.paused.write().unwrap() = *self.paused.read().unwrap();
+        *new.generation.write().unwrap() = *self.generation.read().unwrap();
+        let size = new.texture_size;
+        if grid.rescale {
+            new.stamp(&board.scaled(size.width, size.height), 0, 0);
+        } else {
+            new.stamp(
+                &board,
+                (size.width as i32 - board.width as i32) / 2,
+                (size.height as i32 - board.height as i32) / 2,
+            );
+        }
+        new.fit_canvas();
+        new.reset_view();
+        new.update();
+        warn!(
+            "Board resized from {}x{} to {}x{} cells{}",
+            board.width,
+            board.height,
+            size.width,
+            size.height,
+            if grid.rescale { ", rescaled" } else { "" }
+        );
+        Ok(new)
+    }
+
+    /// Counts the live cells of the most recent generation.
+    async fn population(&self) -> Result<u32, String> {
+        let texels = self
+            .read_region(0, 0, self.texture_size.width, self.texture_size.height)
+            .await?;
+        Ok(self
+            .decode_texels(&texels)
+            .into_iter()
+            .filter(|[r, g, b, _]| r * r + g * g + b * b > 0.5)
+            .count() as u32)
+    }
+
+    /// Runs random soups for `SOUP_GENERATIONS` generations each until searching is switched off,
+    /// logging every soup whose final population beats the best one so far.
+    async fn soup_search(&self) {
+        let was_paused = *self.paused.read().unwrap();
+        *self.paused.write().unwrap() = true;
+
+        let mut best = 0;
+        let mut seed = js_sys::Date::now() as u64;
+        warn!("Soup search started");
+        while *self.searching.read().unwrap() {
+            self.fill_soup(seed);
+            for _ in 0..SOUP_GENERATIONS {
+                self.step();
+            }
+
+            let population = match self.population().await {
+                Ok(population) => population,
+                Err(e) => {
+                    warn!("Could not count the soup: {}", e);
+                    *self.searching.write().unwrap() = false;
+                    break;
+                }
+            };
+            if population > best {
+                best = population;
+                warn!(
+                    "Soup {:#x} has population {} after {} generations",
+                    seed, population, SOUP_GENERATIONS
+                );
+            }
+            seed = seed.wrapping_add(1);
+        }
+        warn!("Soup search stopped");
+
+        *self.paused.write().unwrap() = was_paused;
+    }
+
+    /// Simulates as many generations as possible in `BENCHMARK_MS` without presenting any and
+    /// reports the throughput. The board keeps the generations it advanced.
+    async fn benchmark(&self) {
+        *self.benchmarking.write().unwrap() = true;
+        let was_paused = *self.paused.read().unwrap();
+        *self.paused.write().unwrap() = true;
+        warn!("Benchmark started");
+
+        let performance = web_sys::window().unwrap().performance().unwrap();
+        let start = performance.now();
+        let mut generations = 0;
+        let mut elapsed = 0.0;
+        while elapsed < BENCHMARK_MS {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            for _ in 0..BENCHMARK_BATCH {
+                self.encode_step(&mut encoder);
+            }
+            self.queue.submit(std::iter::once(encoder.finish()));
+            // Reading a texel back waits until the GPU actually finished the batch
+            if let Err(e) = self.read_region(0, 0, 1, 1).await {
+                warn!("Benchmark stopped: {}", e);
+                break;
+            }
+            generations += BENCHMARK_BATCH;
+            elapsed = performance.now() - start;
+        }
+
+        let size = self.texture_size;
+        let report = format!(
+            "{:.0} generations per second ({} generations of {}x{} cells in {:.0}ms)",
+            generations as f64 / (elapsed / 1000.0),
+            generations,
+            size.width,
+            size.height,
+            elapsed
+        );
+        warn!("Benchmark: {}", report);
+        self.benchmark_output.set_text_content(Some(&report));
+        self.benchmark_output.set_hidden(false);
+
+        *self.paused.write().unwrap() = was_paused;
+        *self.benchmarking.write().unwrap() = false;
+    }
+
+    /// Keeps a copy of the live cells to restore the board from if the context is lost. If they
+    /// can not be read back the previous snapshot is kept.
+    async fn take_snapshot(&self) {
+        if *self.context_lost.read().unwrap() {
+            return;
+        }
+        let generation = *self.generation.read().unwrap();
+        match self.board().await {
+            Ok(board) => *self.snapshot.write().unwrap() = Some((generation, board)),
+            Err(e) => warn!("Skipped a snapshot: {}", e),
+        }
+    }
+
+    /// Saves the board and its generation under `slot`, also in `localStorage` if it is
+    /// available and has room, so the slot survives reloads.
+    async fn save_slot(&self, slot: u32) {
+        let generation = *self.generation.read().unwrap();
+        let board = match self.board().await {
+            Ok(board) => board,
+            Err(e) => {
+                warn!("Could not save slot {}: {}", slot, e);
+                return;
+            }
+        };
+        let population = board.cells.iter().filter(|&&alive| alive).count();
+        let stored = format!("{}\n{}\n{}", SLOT_VERSION_TAG, generation, board.to_rle());
+        let storage = web_sys::window().unwrap().local_storage().ok().flatten();
+        let persisted = storage.is_some_and(|storage| {
+            storage
+                .set_item(&format!("{}{}", SLOT_STORAGE_PREFIX, slot), &stored)
+                .is_ok()
+        });
+        self.slots
+            .write()
+            .unwrap()
+            .insert(slot, (generation, board));
+        let message = format!(
+            "Saved generation {} with {} live cells to slot {}{}",
+            generation,
+            population,
+            slot,
+            if persisted {
+                ""
+            } else {
+                ", it is lost on reload since localStorage is unavailable or full"
+            }
+        );
+        self.announce(&message);
+        warn!("{}", message);
+    }
+
+    /// Replaces the first layer with the board saved under `slot` in this session, or in an
+    /// earlier one if it is only in `localStorage`, and goes back to its generation.
+    fn restore_slot(&self, slot: u32) {
+        let saved = self.slots.read().unwrap().get(&slot).cloned();
+        let saved = saved.or_else(|| {
+            let storage = web_sys::window().unwrap().local_storage().ok().flatten()?;
+            let stored = storage
+                .get_item(&format!("{}{}", SLOT_STORAGE_PREFIX, slot))
+                .ok()
+                .flatten()?;
+            parse_slot(&stored)
+        });
+        let Some((generation, pattern)) = saved else {
+            warn!(
+                "Slot {} is empty, Shift+{} saves the board to it",
+                slot, slot
+            );
+            return;
+        };
+
+        // RLE leaves out trailing dead cells, and the board may have been resized since
+        let (width, height) = (self.texture_size.width, self.texture_size.height);
+        let board = Pattern {
+            width,
+            height,
+            cells: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| x < pattern.width && y < pattern.height && pattern.get(x, y))
+                .collect(),
+        };
+        self.stamp(&board, 0, 0);
+        *self.generation.write().unwrap() = generation;
+        let message = format!("Restored generation {} from slot {}", generation, slot);
+        self.announce(&message);
+        warn!("{}", message);
+    }
+
+    /// Adds the current population to the history and redraws the graph, only while it is shown
+    /// since every sample reads the whole board back.
+    async fn sample_population(&self) {
+        if *self.context_lost.read().unwrap() || self.population_graph.hidden() {
+            return;
+        }
+        let generation = *self.generation.read().unwrap();
+        let population = match self.population().await {
+            Ok(population) => population,
+            Err(e) => {
+                warn!("Skipped a population sample: {}", e);
+                return;
+            }
+        };
+        {
+            let mut history = self.population_history.write().unwrap();
+            match history.back() {
+                // The board was cleared or rewound, the old samples belong to another run
+                Some(&(last, _)) if last > generation => history.clear(),
+                // Drawing while paused changes the population of the same generation
+                Some(&(last, _)) if last == generation => {
+                    history.pop_back();
+                }
+                _ => {}
+            }
+            history.push_back((generation, population));
+            while history.len() > MAX_POPULATION_SAMPLES
+                || history
+                    .front()
+                    .is_some_and(|&(first, _)| first + self.population_window < generation)
+            {
+                history.pop_front();
+            }
+        }
+        self.draw_population_graph();
+    }
+
+    /// Draws the population history as a line over the last `population_window` generations,
+    /// scaled to the highest population in it.
+    fn draw_population_graph(&self) {
+        let Some(context) = self
+            .population_graph
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|context| context.dyn_into::<web_sys::CanvasRenderingContext2d>().ok())
+        else {
+            return;
+        };
+        let width = self.population_graph.width() as f64;
+        let height = self.population_graph.height() as f64;
+        let ratio = height / POPULATION_GRAPH_SIZE.1 as f64;
+        context.set_fill_style(&"#111".into());
+        context.fill_rect(0.0, 0.0, width, height);
+
+        let history = self.population_history.read().unwrap();
+        let Some(&(last, population)) = history.back() else {
+            return;
+        };
+        let max = history.iter().map(|&(_, p)| p).max().unwrap_or(0).max(1) as f64;
+        let first = last.saturating_sub(self.population_window);
+        let margin = 4.0 * ratio;
+        let point = |generation: u64, population: u32| {
+            (
+                (generation - first) as f64 / self.population_window as f64 * width,
+                height - margin - population as f64 / max * (height - 2.0 * margin),
+            )
+        };
+
+        context.set_stroke_style(&"#6f6".into());
+        context.set_line_width(ratio);
+        context.begin_path();
+        for (i, &(generation, population)) in history.iter().enumerate() {
+            let (x, y) = point(generation, population);
+            if i == 0 {
+                context.move_to(x, y);
+            } else {
+                context.line_to(x, y);
+            }
+        }
+        context.stroke();
+
+        context.set_fill_style(&"#eee".into());
+        context.set_font(&format!("{}px sans-serif", 11.0 * ratio));
+        let label = format!("{} cells at generation {}, max {}", population, last, max);
+        context.fill_text(&label, margin, 12.0 * ratio).unwrap();
+    }
+
+    /// Shows the extinction message once a running board has no live cells left, and pauses
+    /// if `pause_on_extinction` is set. Drawing, stamping or randomizing hides it again.
+    async fn check_extinction(&self) {
+        if *self.context_lost.read().unwrap()
+            || *self.paused.read().unwrap()
+            || !self.extinct_message.hidden()
+        {
+            return;
+        }
+        // A board that can not be read back is left for the next check
+        if self.population().await != Ok(0) {
+            return;
+        }
+        let generation = *self.generation.read().unwrap();
+        let message = format!("Extinct at generation {}", generation);
+        self.extinct_message.set_text_content(Some(&message));
+        self.extinct_message.set_hidden(false);
+        self.announce(&message);
+        warn!("{}", message);
+        if self.pause_on_extinction {
+            self.set_paused(true);
+        }
+    }
+
+    /// Removes the elements this state added to the page.
+    fn remove_elements(&self) {
+        self.tooltip.remove();
+        self.badge.remove();
+        self.help.remove();
+        self.live_region.remove();
+        self.benchmark_output.remove();
+        self.extinct_message.remove();
+        self.population_graph.remove();
+        self.frame_stats_output.remove();
+    }
+
+    /// Updates `frame_delta` from the `requestAnimationFrame` timestamp of this frame.
+    fn advance_clock(&self, timestamp: f64) {
+        let last_frame_time = self.last_frame_time.write().unwrap().replace(timestamp);
+        let delta = last_frame_time.map_or(0.0, |last| timestamp - last);
+        // requestAnimationFrame stalled, e.g. in a background tab, so this says nothing about
+        // how fast frames are
+        *self.frame_delta.write().unwrap() = if delta > MAX_FRAME_DELTA_MS {
+            0.0
+        } else {
+            delta.max(0.0)
+        };
+
+        let mut stats = self.frame_stats.write().unwrap();
+        stats.since.get_or_insert(timestamp);
+        stats.frames += 1;
+        if delta > self.draw_interval_ms + FRAME_BUDGET_MS && delta <= MAX_FRAME_DELTA_MS {
+            stats.dropped += 1;
+            debug!("Frame took {:.1}ms", delta);
+        }
+    }
+
+    /// Scales the brush by how fast the stroke moved since the previous frame while tapering, thin
+    /// when slow and thick when fast, eased over a few frames so the stroke does not jitter.
+    fn taper_brush(&self) {
+        let mousepos = *self.last_mousepos.read().unwrap();
+        let previous = std::mem::replace(&mut *self.taper_mousepos.write().unwrap(), mousepos);
+        let mut uniforms = self.uniforms.write().unwrap();
+        let drawing = *self.mousedown.read().unwrap()
+            && !*self.selecting.read().unwrap()
+            && !*self.panning.read().unwrap();
+        if !*self.taper.read().unwrap() || !drawing {
+            uniforms.brush_scale = 1.0;
+            return;
+        }
+        let delta = *self.frame_delta.read().unwrap();
+        let (Some((x, y)), Some((px, py))) = (mousepos, previous) else {
+            return;
+        };
+        if delta <= 0.0 {
+            return;
+        }
+        let distance = (x as f64 - px as f64).hypot(y as f64 - py as f64);
+        let target = (distance / delta * 1000.0 / TAPER_SPEED) as f32;
+        // Whatever the speed, the brush stays in the radius range of [ and ]
+        let radius = uniforms.brush_radius;
+        let target = target
+            .clamp(TAPER_MIN_SCALE, TAPER_MAX_SCALE)
+            .clamp(1.0 / radius, 64.0 / radius);
+        uniforms.brush_scale += (target - uniforms.brush_scale) * TAPER_EASING;
+    }
+
+    /// Puts the canvas into fullscreen, or leaves fullscreen again. The `fullscreenchange` that
+    /// follows refits the surface to the new size, and leaving restores the page layout around
+    /// the canvas as it was.
+    fn toggle_fullscreen(&self) {
+        let doc = web_sys::window().unwrap().document().unwrap();
+        if doc.fullscreen_element().is_some() {
+            doc.exit_fullscreen();
+            return;
+        }
+        // web-sys drops the promise, which rejects e.g. if the key press no longer counts as a
+        // user gesture by the time it is handled
+        let request = js_sys::Reflect::get(&self.canvas, &"requestFullscreen".into())
+            .ok()
+            .and_then(|request| request.dyn_into::<js_sys::Function>().ok());
+        let Some(request) = request else {
+            warn!("Fullscreen is not supported by this browser");
+            return;
+        };
+        let promise = match request.call0(&self.canvas) {
+            Ok(promise) => promise.dyn_into::<js_sys::Promise>().ok(),
+            Err(e) => {
+                warn!("Could not enter fullscreen: {:?}", e);
+                return;
+            }
+        };
+        if let Some(promise) = promise {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = wasm_bindgen_futures::JsFuture::from(promise).await {
+                    warn!("Could not enter fullscreen: {:?}", e);
+                }
+            });
+        }
+    }
+
+    /// Starts counting frames over, after a setting changed.
+    fn reset_frame_stats(&self) {
+        *self.frame_stats.write().unwrap() = FrameStats::default();
+    }
+
+    /// Writes the frame rate and the dropped frames into the statistics below the canvas, and
+    /// logs the dropped frames whenever there are more than the last time.
+    fn show_frame_stats(&self) {
+        if self.frame_stats_output.hidden() {
+            return;
+        }
+        let now = web_sys::window().unwrap().performance().unwrap().now();
+        let mut stats = self.frame_stats.write().unwrap();
+        let elapsed = stats.since.map_or(0.0, |since| now - since);
+        let fps = if elapsed > 0.0 {
+            stats.frames as f64 / (elapsed / 1000.0)
+        } else {
+            0.0
+        };
+        let report = format!(
+            "{:.0} fps, {} of {} generations per frame, {} of {} frames dropped",
+            fps,
+            *self.steps_per_frame.read().unwrap(),
+            *self.target_steps_per_frame.read().unwrap(),
+            stats.dropped,
+            stats.frames
+        );
+        self.frame_stats_output.set_text_content(Some(&report));
+        if stats.dropped > stats.logged_dropped {
+            warn!("{}", report);
+            stats.logged_dropped = stats.dropped;
+        }
+    }
+
+    /// Halves `steps_per_frame` when the last frame took longer than `FRAME_BUDGET_MS` and
+    /// ramps it back up towards the requested count while frames stay within it. Backing off
+    /// and reaching the requested count are logged, the F statistics show the count.
+    ///
+    /// Frames are timed from one `requestAnimationFrame` to the next, so they never take less
+    /// than the refresh interval of the display however little work they do.
+    fn throttle(&self) {
+        let frame_time = *self.frame_delta.read().unwrap();
+        if frame_time == 0.0 {
+            return;
+        }
+
+        let target = *self.target_steps_per_frame.read().unwrap();
+        let mut steps = self.steps_per_frame.write().unwrap();
+        let slow = frame_time > FRAME_BUDGET_MS;
+        let adjusted = if slow {
+            (*steps / 2).clamp(1, target)
+        } else {
+            (*steps + 1).min(target)
+        };
+        if slow && adjusted < *steps {
+            warn!(
+                "Frame took {:.1}ms, backing off to {} of {} generations per frame",
+                frame_time, adjusted, target
+            );
+        } else if adjusted > *steps && adjusted == target {
+            warn!("Reached {} generations per frame", target);
+        }
+        *steps = adjusted;
+    }
+
+    /// Cuts `steps` generations from now short at `max_generations`, pausing if they reach it.
+    fn limit_steps(&self, steps: u64) -> u64 {
+        let limit = *self.max_generations.read().unwrap();
+        let Some(limit) = limit else {
+            return steps;
+        };
+        let generation = *self.generation.read().unwrap();
+        let steps = steps.min(limit.saturating_sub(generation));
+        if generation + steps >= limit {
+            *self.max_generations.write().unwrap() = None;
+            *self.paused.write().unwrap() = true;
+            warn!("Reached generation {}, paused", limit);
+        }
+        steps
+    }
+
+    /// Records the generations due at `timestamp`: `steps_per_frame` of them every frame, or
+    /// every `step_interval_ms` if that is set, or one every `interpolation_ms` in slow motion.
+    fn encode_generations(&self, encoder: &mut wgpu::CommandEncoder, timestamp: f64) {
+        if *self.paused.read().unwrap() {
+            return;
+        }
+        let interpolate = *self.interpolate.read().unwrap();
+        let interval = if interpolate {
+            self.interpolation_ms
+        } else {
+            *self.step_interval_ms.read().unwrap()
+        };
+        if timestamp - *self.last_step_time.read().unwrap() < interval {
+            return;
+        }
+
+        let mut steps = if interpolate {
+            1
+        } else {
+            *self.steps_per_frame.read().unwrap() as u64
+        };
+        steps = self.limit_steps(steps);
+        if steps == 0 {
+            return;
+        }
+        // Showing changes compares against the board before the whole batch
+        if interpolate || self.uniforms.read().unwrap().show_changes == 1 {
+            self.save_previous(encoder);
+        }
+        *self.last_step_time.write().unwrap() = timestamp;
+        for _ in 0..steps {
+            self.encode_step(encoder);
+            self.apply_scheduled_stamps(encoder);
+        }
+    }
+
+    /// Draws a frame, `timestamp` is the time passed to the `requestAnimationFrame` callback.
+    ///
+    /// The uniforms are uploaded at the start of every frame, not only after input events, so
+    /// `time` and anything else that changes without input is current in every pass.
+    fn render(&self, timestamp: f64) -> Result<(), wgpu::SurfaceError> {
+        if *self.context_lost.read().unwrap() || *self.jumping.read().unwrap() {
+            return Ok(());
+        }
+        if timestamp - *self.last_draw_time.read().unwrap() < self.draw_interval_ms {
+            return Ok(());
+        }
+        *self.last_draw_time.write().unwrap() = timestamp;
+        self.advance_clock(timestamp);
+        self.advance_view_tween(timestamp);
+        self.taper_brush();
+        self.update();
+        self.throttle();
+
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            self.encode_generations(&mut encoder, timestamp);
+            {
+                // Starts over from the previous generation with every step
+                let mut uniforms = self.uniforms.write().unwrap();
+                let interpolate = *self.interpolate.read().unwrap();
+                let step_interval = *self.step_interval_ms.read().unwrap();
+                let since_step = timestamp - *self.last_step_time.read().unwrap();
+                uniforms.blend = if interpolate {
+                    (since_step / self.interpolation_ms).clamp(0.0, 1.0) as f32
+                } else {
+                    1.0
+                };
+                uniforms.ms_per_generation = if interpolate {
+                    self.interpolation_ms
+                } else if step_interval > 0.0 {
+                    step_interval
+                } else {
+                    *self.frame_delta.read().unwrap() / *self.steps_per_frame.read().unwrap() as f64
+                } as f32;
+                // Advancing every frame, a generation is on screen for the whole frame it was
+                // computed in, so it counts as already shown for that long
+                uniforms.since_step_ms = if interpolate || step_interval > 0.0 {
+                    since_step as f32
+                } else {
+                    (since_step as f32).max(uniforms.ms_per_generation)
+                };
+                self.queue.write_buffer(
+                    &self.uniforms_buffer,
+                    0,
+                    bytemuck::cast_slice(&[*uniforms]),
+                );
+            }
+            self.encode_draw(&mut encoder);
+            self.encode_display_mips(&mut encoder);
+
+            {
+                let msaa_view = self.msaa_view.read().unwrap();
+                let (target, resolve_target) = match msaa_view.as_ref() {
+                    Some(msaa_view) => (msaa_view, Some(&view)),
+                    None => (&view, None),
+                };
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("render pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: target,
+                        resolve_target,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(BACKGROUND),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+
+                render_pass.set_pipeline(&self.render_pipeline);
+                render_pass.set_bind_group(0, &self.texture_target_bind_group, &[]);
+                render_pass.set_bind_group(1, &self.uniforms_bind_group, &[]);
+                render_pass.set_bind_group(2, &self.layers[0].rule_bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+/// Handle to a running simulation, returned by `run()`.
+///
+/// ```js
+/// const life = await run();
+/// life.pause();
+/// life.load_rle("x = 3, y = 3\nbo$2bo$3o!");
+/// life.step();
+/// life.play();
+/// life.stop();
+/// ```
+///
+/// Stopping, or freeing the handle from JavaScript, cancels the animation loop and removes every
+/// event listener so the canvas can be reused or unmounted.
+#[wasm_bindgen]
+pub struct Simulation {
+    state: SharedState,
+    sender: tokio::sync::mpsc::UnboundedSender<CanvasEvent>,
+    /// Id of the next scheduled animation frame
+    animation_frame: Rc<Cell<i32>>,
+    /// The animation frame callback, it owns the only other reference to `state`
+    frame_callback: FrameCallback,
+    _listeners: Vec<Listener>,
+    /// Dropdown of the pattern library below the canvas
+    library: web_sys::HtmlSelectElement,
+    /// File input and threshold slider for seeding the board from an image
+    image_seeding: web_sys::Element,
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        let _ = web_sys::window()
+            .unwrap()
+            .cancel_animation_frame(self.animation_frame.get());
+        self.frame_callback.borrow_mut().take();
+        self.state().remove_elements();
+        self.library.remove();
+        self.image_seeding.remove();
+    }
+}
+
+impl Simulation {
+    /// The current state, which is replaced when recovering from a context loss.
+    fn state(&self) -> Arc<State> {
+        Arc::clone(&self.state.borrow())
+    }
+}
+
+#[wasm_bindgen]
+impl Simulation {
+    /// Resumes advancing one or more generations per frame.
+    pub fn play(&self) {
+        let _ = self.sender.send(CanvasEvent::Play);
+    }
+
+    /// Stops advancing generations, the board stays visible and editable.
+    pub fn pause(&self) {
+        let _ = self.sender.send(CanvasEvent::Pause);
+    }
+
+    /// Whether the simulation is currently paused.
+    pub fn paused(&self) -> bool {
+        *self.state().paused.read().unwrap()
+    }
+
+    /// Advances exactly one generation. Only has an effect while paused.
+    pub fn step(&self) {
+        let _ = self.sender.send(CanvasEvent::Step);
+    }
+
+    /// Kills every cell.
+    pub fn clear(&self) {
+        let _ = self.sender.send(CanvasEvent::Clear);
+    }
+
+    /// Resizes the board to `width` by `height` cells, keeping the settings. The board is
+    /// stretched onto the new size if `rescale` is set and centered on it otherwise.
+    pub fn reconfigure(&self, width: u32, height: u32, rescale: bool) {
+        let _ = self.sender.send(CanvasEvent::Reconfigure(GridConfig {
+            width,
+            height,
+            rescale,
+        }));
+    }
+
+    /// Replaces the board with random cells, each alive with probability `density` in [0, 1].
+    pub fn randomize(&self, density: f32) {
+        let _ = self
+            .sender
+            .send(CanvasEvent::Randomize(Gradient::Uniform(density), None));
+    }
+
+    /// Replaces the board with random cells whose density varies across it. `gradient` is one
+    /// of `0.3` for a uniform density, `horizontal:0.1:0.6` or `vertical:0.1:0.6` from the left or
+    /// top edge to the opposite one, or `radial:0.6:0.05` from the center to the corners. The same
+    /// `seed` always gives the same board. Throws if `gradient` does not parse.
+    pub fn randomize_with_gradient(
+        &self,
+        gradient: &str,
+        seed: Option<f64>,
+    ) -> Result<(), JsValue> {
+        let gradient = gradient
+            .parse()
+            .map_err(|_| JsValue::from_str(&format!("Invalid gradient {:?}", gradient)))?;
+        let _ = self.sender.send(CanvasEvent::Randomize(
+            gradient,
+            seed.map(|seed| seed as u64),
+        ));
+        Ok(())
+    }
+
+    /// Rule, speed, display and brush settings as a string for `apply_settings`.
+    pub fn settings(&self) -> String {
+        self.state().serialize_settings()
+    }
+
+    /// Applies settings from `settings()`, skipping any field that is unknown or invalid.
+    pub fn apply_settings(&self, settings: &str) {
+        let state = self.state();
+        state.apply_settings(settings);
+        state.update();
+    }
+
+    /// Stamps an RLE encoded pattern onto the center of the board. Throws if it does not parse.
+    pub fn load_rle(&self, rle: &str) -> Result<(), JsValue> {
+        let pattern = Pattern::parse_rle(rle).map_err(|e| JsValue::from_str(&e))?;
+        let _ = self.sender.send(CanvasEvent::LoadPattern(pattern, None));
+        Ok(())
+    }
+
+    /// Resolves to the live cells as `"rle"`, `"life106"` or `"cells"` text. Throws for any
+    /// other format and rejects if the board can not be read back.
+    pub fn export_pattern(&self, format: &str) -> Result<js_sys::Promise, JsValue> {
+        let format = format
+            .parse::<Format>()
+            .map_err(|e| JsValue::from_str(&e))?;
+        let state = self.state();
+        Ok(wasm_bindgen_futures::future_to_promise(async move {
+            let pattern = state
+                .board_pattern()
+                .await
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(JsValue::from_str(&pattern.export(format)))
+        }))
+    }
+
+    /// Resolves to the number of generations after which the board is back to how it is now: 1
+    /// for a still life, the period of an oscillator, or 0 while it is still changing or repeats
+    /// less often than every 30 generations. Spaceships count as changing.
+    ///
+    /// Reads the board back and runs it on the CPU, so it takes longer the more the board spans,
+    /// meant for polling a few times per second at most. Rejects for rules other than B/S rules
+    /// with synchronous updates.
+    pub fn period(&self) -> js_sys::Promise {
+        let state = self.state();
+        wasm_bindgen_futures::future_to_promise(async move {
+            state
+                .period()
+                .await
+                .map(JsValue::from)
+                .map_err(|e| JsValue::from_str(&e))
+        })
+    }
+
+    /// Starts logging input events, replacing any unfinished recording.
+    pub fn start_recording(&self) {
+        self.state().start_recording();
+    }
+
+    /// Stops logging input and returns the log, one event per line, for `replay`.
+    pub fn stop_recording(&self) -> String {
+        self.state().stop_recording()
+    }
+
+    /// Plays back a log from `stop_recording` at the pace it was recorded.
+    pub fn replay(&self, log: String) {
+        let state = self.state();
+        wasm_bindgen_futures::spawn_local(async move {
+            state.replay(&log).await;
+        });
+    }
+
+    /// Flips `flips` random cells of the running board, the same cells for the same generation.
+    pub fn inject_noise(&self, flips: u32) {
+        let _ = self.sender.send(CanvasEvent::InjectNoise(flips));
+    }
+
+    /// Stamps an RLE pattern centered on cell (`x`, `y`) every `interval` generations, as if a
+    /// gun off the board shot it there. Throws if the pattern does not parse.
+    pub fn schedule_stamp(&self, rle: &str, x: u32, y: u32, interval: u32) -> Result<(), JsValue> {
+        let pattern = Pattern::parse_rle(rle).map_err(|e| JsValue::from_str(&e))?;
+        self.state().schedule_stamp(pattern, x, y, interval as u64);
+        Ok(())
+    }
+
+    /// Removes every stamp `schedule_stamp` or q scheduled.
+    pub fn clear_stamps(&self) {
+        self.state().scheduled_stamps.write().unwrap().clear();
+    }
+
+    /// Compiles a WGSL snippet as the body of the custom rule and switches to it, see
+    /// `custom.wgsl` for the variables it can use. Rejects with the compiler message if it does
+    /// not compile, the previous rule keeps running then.
+    pub fn set_rule_wgsl(&self, body: String) -> js_sys::Promise {
+        let state = self.state();
+        wasm_bindgen_futures::future_to_promise(async move {
+            state
+                .set_custom_rule(&body)
+                .await
+                .map_err(|e| JsValue::from_str(&e))?;
+            state.update();
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// Switches the first layer to a rule in B/S notation such as `"B36/S23"`, the board keeps
+    /// evolving under the new rule. Throws if the rule does not parse.
+    pub fn set_rule(&self, rule: &str) -> Result<(), JsValue> {
+        let parsed: Rule = rule
+            .parse()
+            .map_err(|_| JsValue::from_str(&format!("Invalid rule {:?}", rule)))?;
+        let state = self.state();
+        state.layers[0].set_rule(&state.queue, parsed);
+        warn!("Rule: {}", parsed);
+        Ok(())
+    }
+
+    /// Shows cells in the comma separated hex colors of `palette` by age, e.g.
+    /// `"000000,ffffff,ff8000"`: dead cells in the first color, newborn ones in the second and the
+    /// oldest in the last. An empty string shows cells in their own colors again. Throws if a
+    /// color does not parse.
+    pub fn set_palette(&self, palette: &str) -> Result<(), JsValue> {
+        let palette = palette
+            .parse()
+            .map_err(|_| JsValue::from_str(&format!("Invalid palette {:?}", palette)))?;
+        let state = self.state();
+        state.set_palette(&mut state.uniforms.write().unwrap(), palette);
+        state.update();
+        Ok(())
+    }
+
+    /// Mirrors the pointer vertically, for setups where drawing appears upside down.
+    pub fn set_flip_y(&self, flip_y: bool) {
+        *self.state().flip_y.write().unwrap() = flip_y;
+    }
+
+    /// Tears the simulation down, the handle can not be used afterwards.
+    pub fn stop(self) {}
+}
+
+thread_local! {
+    /// `describe_adapter` of the adapter found by the first simulation or `graphics_backend` call
+    static ADAPTER_INFO: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The graphics API and, where the browser reveals it, the adapter name, e.g. `WebGL2: ANGLE (...)`.
+fn describe_adapter(info: &wgpu::AdapterInfo) -> String {
+    let backend = match info.backend {
+        wgpu::Backend::BrowserWebGpu => "WebGPU".to_owned(),
+        wgpu::Backend::Gl => "WebGL2".to_owned(),
+        backend => format!("{:?}", backend),
+    };
+    if info.name.is_empty() {
+        backend
+    } else {
+        format!("{}: {}", backend, info.name)
+    }
+}
+
+/// Which graphics API simulations run on, `WebGPU` or `WebGL2`, followed by the adapter name if
+/// the browser reveals it. Works before `run()` without starting a simulation, only the first call
+/// looks for an adapter. Throws if there is none.
+#[wasm_bindgen]
+pub async fn graphics_backend() -> Result<String, JsValue> {
+    if let Some(info) = ADAPTER_INFO.with(|cache| cache.borrow().clone()) {
+        return Ok(info);
+    }
+
+    // WebGL adapters need a surface to be found, a canvas that is never added to the page will do
+    let canvas = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("canvas")?
+        .dyn_into::<web_sys::HtmlCanvasElement>()?;
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        dx12_shader_compiler: Default::default(),
+    });
+    let surface = instance
+        .create_surface_from_canvas(&canvas)
+        .map_err(|e| JsValue::from_str(&InitError::SurfaceCreation(e).to_string()))?;
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or_else(|| JsValue::from_str(&InitError::NoAdapter.to_string()))?;
+
+    let info = describe_adapter(&adapter.get_info());
+    ADAPTER_INFO.with(|cache| *cache.borrow_mut() = Some(info.clone()));
+    Ok(info)
+}
+
+/// Starts the simulation on the `#canvas` element and returns a handle to control it. Throws,
+/// and shows why on the page, if there is no such canvas or it can not be rendered to.
+#[wasm_bindgen]
+pub async fn run() -> Result<Simulation, JsValue> {
+    run_on("canvas").await
+}
+
+/// Starts an independent simulation on the canvas with id `canvas_id`. Each call gets its own
+/// board, event loop and animation loop, so several can run on one page.
+#[wasm_bindgen]
+pub async fn run_on(canvas_id: &str) -> Result<Simulation, JsValue> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    // Fails if an earlier instance already installed the logger, which is fine
+    let _ = console_log::init_with_level(log::Level::Warn);
+
+    let window = web_sys::window().unwrap();
+    let doc = window.document().unwrap();
+    let show_error = |e: InitError, after: Option<&web_sys::Element>| {
+        error!("{}", e);
+        let message = doc.create_element("p").unwrap();
+        message.set_class_name("error");
+        message.set_text_content(Some(&e.to_string()));
+        match after {
+            Some(element) => element.after_with_node_1(&message).unwrap(),
+            None => {
+                if let Some(body) = doc.body() {
+                    body.append_child(&message).unwrap();
+                }
+            }
+        }
+        JsValue::from_str(&e.to_string())
+    };
+    let Some(element) = doc.get_element_by_id(canvas_id) else {
+        return Err(show_error(
+            InitError::MissingCanvas(canvas_id.to_owned()),
+            None,
+        ));
+    };
+    let canvas = match element.dyn_into::<web_sys::HtmlCanvasElement>() {
+        Ok(canvas) => canvas,
+        Err(element) => {
+            let tag = element.tag_name();
+            return Err(show_error(
+                InitError::NotACanvas(canvas_id.to_owned(), tag),
+                Some(&element),
+            ));
+        }
+    };
+
+    canvas.set_width(1024);
+    canvas.set_height(1024);
+
+    let state = match State::new(&canvas, Settings::from_canvas(&canvas)).await {
+        Ok(state) => Rc::new(RefCell::new(Arc::new(state))),
+        Err(e) => return Err(show_error(e, Some(&canvas))),
+    };
+
+    // A URL like `index.html#rule=B36/S23&speed=4` reproduces someone else's settings
+    let hash = window.location().hash().unwrap_or_default();
+    if let Some(settings) = hash.strip_prefix('#').filter(|s| !s.is_empty()) {
+        let state = state.borrow();
+        state.apply_settings(settings);
+        state.update();
+    }
+
+    // `data-pattern="gosper-glider-gun"` starts with a pattern of the library, before the first
+    // frame so it is there from the start
+    if let Some(name) = canvas.get_attribute("data-pattern") {
+        match library::load_named(&manifest_url(&canvas), &name).await {
+            Ok(pattern) => {
+                let current = Arc::clone(&state.borrow());
+                current
+                    .input(&CanvasEvent::LoadPattern(pattern, None))
+                    .await;
+                current.update();
+            }
+            Err(e) => warn!("Invalid data-pattern, starting empty: {}", e),
+        }
+    }
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let mut listeners = setup_listeners(&canvas, sender.clone());
+    let (library, library_listener) = setup_library(&canvas, sender.clone());
+    listeners.push(library_listener);
+    let (image_seeding, image_listeners) = setup_image_seeding(&library, &state, sender.clone());
+    listeners.extend(image_listeners);
+
+    let animation_frame = Rc::new(Cell::new(0));
+    let frame_callback: FrameCallback = Rc::new(RefCell::new(None));
+    {
+        let state2 = Rc::clone(&state);
+        let window2 = window.clone();
+        let animation_frame2 = Rc::clone(&animation_frame);
+
+        let f = Rc::clone(&frame_callback);
+        *frame_callback.borrow_mut() = Some(Closure::new(move |timestamp: f64| {
+            let state = Arc::clone(&state2.borrow());
+            match state.render(timestamp) {
+                Ok(()) => {}
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                    state
+                        .surface
+                        .configure(&state.device, &state.config.read().unwrap());
+                }
+                Err(e) => warn!("Skipping frame: {}", e),
+            }
+            if let Some(callback) = f.borrow().as_ref() {
+                animation_frame2.set(
+                    window2
+                        .request_animation_frame(callback.as_ref().unchecked_ref())
+                        .unwrap(),
+                );
+            }
+        }));
+
+        animation_frame.set(
+            window
+                .request_animation_frame(
+                    frame_callback
+                        .borrow()
+                        .as_ref()
+                        .unwrap()
+                        .as_ref()
+                        .unchecked_ref(),
+                )
+                .unwrap(),
+        );
+    }
+
+    {
+        let state = Rc::clone(&state);
+        let canvas = canvas.clone();
+        // Ends once the handle and all listeners, which own the senders, are dropped
+        wasm_bindgen_futures::spawn_local(async move {
+            while let Some(event) = receiver.recv().await {
+                match event {
+                    CanvasEvent::ContextRestored => {
+                        recover(&canvas, &state).await;
+                        continue;
+                    }
+                    CanvasEvent::Reconfigure(grid) => {
+                        reconfigure(&state, grid).await;
+                        continue;
+                    }
+                    _ => {}
+                }
+                let current = Arc::clone(&state.borrow());
+                current.input(&event).await;
+                current.update();
+            }
+        });
+    }
+
+    {
+        // Holding on weakly lets the snapshots stop once the simulation is torn down
+        let state = Rc::downgrade(&state);
+        wasm_bindgen_futures::spawn_local(async move {
+            loop {
+                sleep(SNAPSHOT_INTERVAL_MS).await;
+                let Some(state) = state.upgrade() else {
+                    break;
+                };
+                let current = Arc::clone(&state.borrow());
+                current.take_snapshot().await;
+            }
+        });
+    }
+
+    {
+        let state = Rc::downgrade(&state);
+        wasm_bindgen_futures::spawn_local(async move {
+            loop {
+                sleep(EXTINCTION_CHECK_INTERVAL_MS).await;
+                let Some(state) = state.upgrade() else {
+                    break;
+                };
+                let current = Arc::clone(&state.borrow());
+                current.check_extinction().await;
+            }
+        });
+    }
+
+    {
+        let state = Rc::downgrade(&state);
+        wasm_bindgen_futures::spawn_local(async move {
+            loop {
+                sleep(POPULATION_SAMPLE_INTERVAL_MS).await;
+                let Some(state) = state.upgrade() else {
+                    break;
+                };
+                let current = Arc::clone(&state.borrow());
+                current.sample_population().await;
+            }
+        });
+    }
+
+    {
+        let state = Rc::downgrade(&state);
+        wasm_bindgen_futures::spawn_local(async move {
+            loop {
+                sleep(FRAME_STATS_INTERVAL_MS).await;
+                let Some(state) = state.upgrade() else {
+                    break;
+                };
+                state.borrow().show_frame_stats();
+            }
+        });
+    }
+
+    Ok(Simulation {
+        state,
+        sender,
+        animation_frame,
+        frame_callback,
+        _listeners: listeners,
+        library,
+        image_seeding,
+    })
+}
+
+/// The state shared by the animation loop, the event loop and the handle. Recovering from a
+/// context loss swaps in a freshly created one.
+type SharedState = Rc<RefCell<Arc<State>>>;
+
+/// The `requestAnimationFrame` callback, shared so it can schedule itself again and the handle
+/// can drop it.
+type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+/// Rebuilds the state from scratch after the context was restored, keeping the settings and the
+/// board of the last snapshot.
+async fn recover(canvas: &web_sys::HtmlCanvasElement, state: &SharedState) {
+    let old = Arc::clone(&state.borrow());
+    let new = match State::new(canvas, Settings::from_canvas(canvas)).await {
+        Ok(new) => new,
+        Err(e) => {
+            error!("Could not recover from the context loss: {}", e);
+            return;
+        }
+    };
+
+    old.remove_elements();
+    new.apply_settings(&old.serialize_settings());
+    *new.paused.write().unwrap() = *old.paused.read().unwrap();
+    let snapshot = old.snapshot.write().unwrap().take();
+    match snapshot {
+        Some((generation, pattern)) => {
+            new.stamp(&pattern, 0, 0);
+            *new.generation.write().unwrap() = generation;
+            warn!(
+                "Recovered from the context loss, restored the board from generation {}",
+                generation
+            );
+        }
+        None => warn!("Recovered from the context loss, no board to restore yet"),
+    }
+    new.update();
+    *state.borrow_mut() = Arc::new(new);
+}
+
+/// Swaps in a state with a board of another size once it is completely built, logging why if
+/// it could not be.
+async fn reconfigure(state: &SharedState, grid: GridConfig) {
+    let old = Arc::clone(&state.borrow());
+    match old.reconfigured(grid).await {
+        Ok(new) => {
+            old.remove_elements();
+            *state.borrow_mut() = Arc::new(new);
         }
+        Err(e) => error!("Could not resize the board, keeping the old one: {}", e),
     }
+}
 
-    fn input(&self, event: &CanvasEvent) -> bool {
-        warn!("{:?}", &event);
-        match event {
-            CanvasEvent::MouseDown => {
-                *self.mousedown.write().unwrap() = true;
-                *self.start_mousepos.write().unwrap() = *self.last_mousepos.read().unwrap();
-            }
-            CanvasEvent::MouseUp => {
-                *self.mousedown.write().unwrap() = false;
+/// The pattern library of `canvas`, `data-pattern-manifest`.
+fn manifest_url(canvas: &web_sys::HtmlCanvasElement) -> String {
+    canvas
+        .get_attribute("data-pattern-manifest")
+        .unwrap_or_else(|| "patterns/manifest.json".to_owned())
+}
+
+/// Adds a dropdown below the canvas listing the patterns of the manifest at
+/// `data-pattern-manifest`, picking one stamps it onto the center of the board. The dropdown
+/// stays disabled if the manifest can not be loaded.
+fn setup_library(
+    canvas: &web_sys::HtmlCanvasElement,
+    sender: tokio::sync::mpsc::UnboundedSender<CanvasEvent>,
+) -> (web_sys::HtmlSelectElement, Listener) {
+    let doc = web_sys::window().unwrap().document().unwrap();
+    let manifest_url = manifest_url(canvas);
+
+    let select = doc
+        .create_element("select")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlSelectElement>()
+        .unwrap();
+    select.set_class_name("library");
+    select.set_disabled(true);
+    let placeholder = doc.create_element("option").unwrap();
+    placeholder.set_text_content(Some("Pattern library"));
+    select.append_child(&placeholder).unwrap();
+    canvas.after_with_node_1(&select).unwrap();
+
+    let entries = Rc::new(RefCell::new(Vec::<library::Entry>::new()));
+    let cache = Rc::new(RefCell::new(library::Cache::new()));
+
+    let select2 = select.clone();
+    let entries2 = Rc::clone(&entries);
+    let listener = Listener::new(&select, "change", move |_event: web_sys::Event| {
+        // The first option is the placeholder
+        let index = select2.selected_index();
+        select2.set_selected_index(0);
+        let entry = (index as usize)
+            .checked_sub(1)
+            .and_then(|i| entries2.borrow().get(i).cloned());
+        let Some(entry) = entry else {
+            return;
+        };
+        let cache = Rc::clone(&cache);
+        let sender = sender.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match library::load(&cache, &entry).await {
+                Ok(pattern) => {
+                    let _ = sender.send(CanvasEvent::LoadPattern(pattern, None));
+                }
+                Err(e) => warn!("Could not load {}: {}", entry.name, e),
             }
-            CanvasEvent::MouseMove(x, y) => {
-                let old_mousepos = *self.last_mousepos.read().unwrap();
-                *self.last_mousepos.write().unwrap() = Some((*x, *y));
-                if !*self.mousedown.read().unwrap() || old_mousepos.is_none() {
-                    return false;
+        });
+    });
+
+    let select2 = select.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let manifest = library::fetch_text(&manifest_url)
+            .await
+            .and_then(|text| library::parse_manifest(&text));
+        match manifest {
+            Ok(manifest) => {
+                for entry in &manifest {
+                    let option = doc.create_element("option").unwrap();
+                    option.set_text_content(Some(&entry.name));
+                    select2.append_child(&option).unwrap();
                 }
+                *entries.borrow_mut() = manifest;
+                select2.set_disabled(false);
             }
-            _ => {}
+            Err(e) => warn!("Pattern library unavailable: {}", e),
         }
-        false
+    });
+
+    (select, listener)
+}
+
+/// The cells of the straight line from `from` to `to`, both included: one per step along the
+/// longer axis, with the other coordinate rounded half up like Bresenham's algorithm does.
+/// `fs_main` previews the line with the same rounding.
+fn line_cells(from: (u32, u32), to: (u32, u32)) -> Vec<(u32, u32)> {
+    let (dx, dy) = (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64);
+    let steps = dx.abs().max(dy.abs());
+    if steps == 0 {
+        return vec![from];
     }
+    let round = |numerator: i64| (2 * numerator + steps).div_euclid(2 * steps);
+    (0..=steps)
+        .map(|i| {
+            (
+                (from.0 as i64 + round(i * dx)) as u32,
+                (from.1 as i64 + round(i * dy)) as u32,
+            )
+        })
+        .collect()
+}
 
-    fn update(&self) {
-        let MOUSE_INACTIVE = [-1000.0, 0.0];
-        let mut mousepos = self
-            .last_mousepos
-            .read()
-            .unwrap()
-            .map_or(MOUSE_INACTIVE, |(x, y)| [x as f32, y as f32]);
-        let mut seed = self
-            .start_mousepos
-            .read()
-            .unwrap()
-            .map_or(MOUSE_INACTIVE, |(x, y)| [x as f32, y as f32]);
+/// Decodes a PNG or JPEG and scales it to fit a `board_size` board, keeping its aspect ratio.
+/// Transparent pixels count as white.
+fn fit_image(bytes: &[u8], board_size: wgpu::Extent3d) -> Result<image::GrayImage, String> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| format!("Could not decode the image: {}", e))?
+        .to_luma_alpha8();
+    let scale = (board_size.width as f32 / image.width() as f32)
+        .min(board_size.height as f32 / image.height() as f32);
+    let width = ((image.width() as f32 * scale) as u32).clamp(1, board_size.width);
+    let height = ((image.height() as f32 * scale) as u32).clamp(1, board_size.height);
+    let flattened = image::GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let [luma, alpha] = image.get_pixel(x, y).0;
+        let alpha = alpha as f32 / 255.0;
+        image::Luma([(luma as f32 * alpha + 255.0 * (1.0 - alpha)) as u8])
+    });
+    Ok(image::imageops::resize(
+        &flattened,
+        width,
+        height,
+        image::imageops::FilterType::Triangle,
+    ))
+}
 
-        if !*self.mousedown.read().unwrap() {
-            mousepos = MOUSE_INACTIVE;
+/// Adds a file input for an image and a threshold slider after `after`. The image is scaled to
+/// the board and centered on it as the new board, pixels darker than the threshold are alive.
+/// Moving the slider seeds the board again from the same image.
+fn setup_image_seeding(
+    after: &web_sys::Element,
+    state: &SharedState,
+    sender: tokio::sync::mpsc::UnboundedSender<CanvasEvent>,
+) -> (web_sys::Element, Vec<Listener>) {
+    let doc = web_sys::window().unwrap().document().unwrap();
+    let container = doc.create_element("div").unwrap();
+    container.set_class_name("image-seeding");
+
+    let label = doc.create_element("label").unwrap();
+    label.set_text_content(Some("Seed from image "));
+    let file_input = doc
+        .create_element("input")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap();
+    file_input.set_type("file");
+    file_input.set_accept("image/png,image/jpeg");
+    label.append_child(&file_input).unwrap();
+    container.append_child(&label).unwrap();
+
+    let label = doc.create_element("label").unwrap();
+    label.set_text_content(Some(" Threshold "));
+    let threshold = doc
+        .create_element("input")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap();
+    threshold.set_type("range");
+    threshold.set_min("0");
+    threshold.set_max("255");
+    threshold.set_value("128");
+    label.append_child(&threshold).unwrap();
+    container.append_child(&label).unwrap();
+    after.after_with_node_1(&container).unwrap();
+
+    let image = Rc::new(RefCell::new(None::<image::GrayImage>));
+    let seed = {
+        let image = Rc::clone(&image);
+        let threshold = threshold.clone();
+        move || {
+            let image = image.borrow();
+            let Some(image) = image.as_ref() else {
+                return;
+            };
+            let threshold = threshold.value().parse::<u8>().unwrap_or(128);
+            let pattern = Pattern {
+                width: image.width(),
+                height: image.height(),
+                cells: image.pixels().map(|pixel| pixel.0[0] < threshold).collect(),
+            };
+            let _ = sender.send(CanvasEvent::Clear);
+            let _ = sender.send(CanvasEvent::LoadPattern(pattern, None));
         }
+    };
 
-        warn!("{:?}", &mousepos);
-        self.uniforms.write().unwrap().mouse_pos = mousepos;
-        self.uniforms.write().unwrap().seed = seed;
-        self.queue.write_buffer(
-            &self.uniforms_buffer,
-            0,
-            bytemuck::cast_slice(&[*self.uniforms.read().unwrap()]),
+    let seed2 = seed.clone();
+    let threshold_listener = Listener::new(&threshold, "change", move |_event: web_sys::Event| {
+        seed2();
+    });
+
+    // The board may be resized later, the image is fitted to its size at the time it is picked
+    let state = Rc::downgrade(state);
+    let file_input2 = file_input.clone();
+    let file_listener = Listener::new(&file_input, "change", move |_event: web_sys::Event| {
+        let Some(file) = file_input2.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        let Some(board_size) = state.upgrade().map(|state| state.borrow().texture_size) else {
+            return;
+        };
+        let image = Rc::clone(&image);
+        let seed = seed.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let bytes = match wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => js_sys::Uint8Array::new(&buffer).to_vec(),
+                Err(e) => {
+                    warn!("Could not read {}: {:?}", file.name(), e);
+                    return;
+                }
+            };
+            match fit_image(&bytes, board_size) {
+                Ok(fitted) => {
+                    *image.borrow_mut() = Some(fitted);
+                    seed();
+                }
+                Err(e) => warn!("Could not load {}: {}", file.name(), e),
+            }
+        });
+    });
+
+    (container, vec![threshold_listener, file_listener])
+}
+
+/// An event listener that is removed again when dropped.
+struct Listener {
+    target: web_sys::EventTarget,
+    name: &'static str,
+    closure: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+impl Listener {
+    /// Calls `f` with every `name` event on `target`, cast to the event type `E`.
+    fn new<E: JsCast>(
+        target: &web_sys::EventTarget,
+        name: &'static str,
+        mut f: impl FnMut(E) + 'static,
+    ) -> Self {
+        let closure = Closure::wrap(
+            Box::new(move |event: web_sys::Event| f(event.unchecked_into())) as Box<dyn FnMut(_)>,
         );
+        target
+            .add_event_listener_with_callback(name, closure.as_ref().unchecked_ref())
+            .unwrap();
+        Self {
+            target: target.clone(),
+            name,
+            closure,
+        }
     }
+}
 
-    fn render(&self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = self
+            .target
+            .remove_event_listener_with_callback(self.name, self.closure.as_ref().unchecked_ref());
+    }
+}
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        {
-            {
-                let mut compute_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("compute pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &self.texture_target_view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
-                });
+fn setup_listeners(
+    canvas: &web_sys::HtmlCanvasElement,
+    sender: tokio::sync::mpsc::UnboundedSender<CanvasEvent>,
+) -> Vec<Listener> {
+    let window = web_sys::window().unwrap();
+    let doc = window.document().unwrap();
+    let mut listeners = Vec::new();
 
-                compute_pass.set_pipeline(&self.compute_pipeline);
-                compute_pass.set_bind_group(0, &self.texture_bind_group, &[]);
-                compute_pass.set_bind_group(1, &self.uniforms_bind_group, &[]);
-                compute_pass.draw(0..3, 0..1);
-            }
+    let sender2 = sender.clone();
+    let canvas2 = canvas.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "mousemove",
+        move |event: web_sys::MouseEvent| {
+            let rect = canvas2.get_bounding_client_rect();
+            let width = canvas2.width() as f32;
+            let height = canvas2.height() as f32;
+            let x = event.offset_x() as f32 * (width / rect.width() as f32);
+            let y = event.offset_y() as f32 * (height / rect.height() as f32);
+            let _ = sender2.send(CanvasEvent::MouseMove(x as u32, y as u32));
+        },
+    ));
 
-            {
-                encoder.copy_texture_to_texture(
-                    wgpu::ImageCopyTextureBase {
-                        texture: &self.texture_target,
-                        mip_level: 0,
-                        origin: wgpu::Origin3d::default(),
-                        aspect: wgpu::TextureAspect::All,
-                    },
-                    wgpu::ImageCopyTextureBase {
-                        texture: &self.texture,
-                        mip_level: 0,
-                        origin: wgpu::Origin3d::default(),
-                        aspect: wgpu::TextureAspect::All,
-                    },
-                    self.texture_size,
+    let sender2 = sender.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "mousedown",
+        move |event: web_sys::MouseEvent| {
+            let _ = sender2.send(CanvasEvent::MouseDown {
+                shift: event.shift_key(),
+                alt: event.alt_key(),
+            });
+        },
+    ));
+
+    let sender2 = sender.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "mouseup",
+        move |_event: web_sys::MouseEvent| {
+            let _ = sender2.send(CanvasEvent::MouseUp);
+        },
+    ));
+
+    let sender2 = sender.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "dblclick",
+        move |_event: web_sys::MouseEvent| {
+            let _ = sender2.send(CanvasEvent::ResetView);
+        },
+    ));
+
+    // Cancelling the loss tells the browser that the context should be restored
+    let sender2 = sender.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "webglcontextlost",
+        move |event: web_sys::Event| {
+            event.prevent_default();
+            let _ = sender2.send(CanvasEvent::ContextLost);
+        },
+    ));
+
+    let sender2 = sender.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "webglcontextrestored",
+        move |_event: web_sys::Event| {
+            let _ = sender2.send(CanvasEvent::ContextRestored);
+        },
+    ));
+
+    // Rotating a phone fires a burst of resizes, only the last one reconfigures the surface
+    let pending_resize = Rc::new(Cell::new(None::<i32>));
+    let sender2 = sender.clone();
+    let send_resize = Rc::new(Closure::<dyn FnMut()>::new(move || {
+        let _ = sender2.send(CanvasEvent::Resize);
+    }));
+    for name in ["resize", "orientationchange"] {
+        let pending_resize = Rc::clone(&pending_resize);
+        let send_resize = Rc::clone(&send_resize);
+        let window2 = window.clone();
+        listeners.push(Listener::new(
+            &window,
+            name,
+            move |_event: web_sys::Event| {
+                if let Some(pending) = pending_resize.take() {
+                    window2.clear_timeout_with_handle(pending);
+                }
+                let timeout = window2.set_timeout_with_callback_and_timeout_and_arguments_0(
+                    (*send_resize).as_ref().unchecked_ref(),
+                    RESIZE_DEBOUNCE_MS,
                 );
-            }
+                pending_resize.set(timeout.ok());
+            },
+        ));
+    }
+
+    // Entering and leaving fullscreen resize the canvas at once, no need to wait for a burst
+    let sender2 = sender.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "fullscreenchange",
+        move |_event: web_sys::Event| {
+            let _ = sender2.send(CanvasEvent::Resize);
+        },
+    ));
+
+    // Keys go to the focused canvas, or to the hovered one while no canvas has focus, so that
+    // several simulations on a page do not all react to the same key
+    canvas.set_tab_index(0);
+    let hovered = Rc::new(Cell::new(false));
+
+    let hovered2 = Rc::clone(&hovered);
+    listeners.push(Listener::new(
+        canvas,
+        "mouseenter",
+        move |_event: web_sys::MouseEvent| hovered2.set(true),
+    ));
+
+    let sender2 = sender.clone();
+    let hovered2 = Rc::clone(&hovered);
+    listeners.push(Listener::new(
+        canvas,
+        "mouseleave",
+        move |_event: web_sys::MouseEvent| {
+            hovered2.set(false);
+            let _ = sender2.send(CanvasEvent::MouseLeave);
+        },
+    ));
+
+    let sender2 = sender.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "wheel",
+        move |event: web_sys::WheelEvent| {
+            // Also keeps the page from scrolling while the cursor is over the canvas
+            event.prevent_default();
+            let _ = sender2.send(if event.alt_key() {
+                // Some systems turn Alt+wheel into horizontal scrolling
+                let delta = if event.delta_y() != 0.0 {
+                    event.delta_y()
+                } else {
+                    event.delta_x()
+                };
+                CanvasEvent::SpeedWheel(delta)
+            } else {
+                CanvasEvent::Wheel(event.delta_y())
+            });
+        },
+    ));
+
+    // Without cancelling dragover the browser would open the dropped file instead
+    listeners.push(Listener::new(
+        canvas,
+        "dragover",
+        |event: web_sys::DragEvent| event.prevent_default(),
+    ));
+
+    let sender2 = sender.clone();
+    let canvas2 = canvas.clone();
+    listeners.push(Listener::new(
+        canvas,
+        "drop",
+        move |event: web_sys::DragEvent| {
+            event.prevent_default();
+            let Some(file) = event
+                .data_transfer()
+                .and_then(|data| data.files())
+                .and_then(|files| files.get(0))
+            else {
+                return;
+            };
+
+            let rect = canvas2.get_bounding_client_rect();
+            let x = event.offset_x() as f32 * (canvas2.width() as f32 / rect.width() as f32);
+            let y = event.offset_y() as f32 * (canvas2.height() as f32 / rect.height() as f32);
+
+            let sender = sender2.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let text = match wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                    Ok(text) => text.as_string().unwrap_or_default(),
+                    Err(e) => {
+                        warn!("Could not read {}: {:?}", file.name(), e);
+                        return;
+                    }
+                };
+                match Pattern::parse(&file.name(), &text) {
+                    Ok(pattern) => {
+                        let _ = sender.send(CanvasEvent::LoadPattern(
+                            pattern,
+                            Some((x as u32, y as u32)),
+                        ));
+                    }
+                    Err(e) => warn!("Could not load {}: {}", file.name(), e),
+                }
+            });
+        },
+    ));
+
+    let sender2 = sender.clone();
+    let doc2 = doc.clone();
+    listeners.push(Listener::new(
+        &doc,
+        "visibilitychange",
+        move |_event: web_sys::Event| {
+            let _ = sender2.send(CanvasEvent::Visibility(!doc2.hidden()));
+        },
+    ));
+
+    for (name, focused) in [("blur", false), ("focus", true)] {
+        let sender2 = sender.clone();
+        listeners.push(Listener::new(
+            &window,
+            name,
+            move |_event: web_sys::Event| {
+                let _ = sender2.send(CanvasEvent::Focus(focused));
+            },
+        ));
+    }
 
+    let sender2 = sender;
+    let canvas2 = canvas.clone();
+    let doc2 = doc.clone();
+    listeners.push(Listener::new(
+        &window,
+        "keydown",
+        move |event: web_sys::KeyboardEvent| {
+            let focused = doc2.active_element();
+            let any_canvas_focused = focused
+                .as_ref()
+                .is_some_and(|element| element.tag_name() == "CANVAS");
+            let ours = match focused {
+                Some(element) if any_canvas_focused => element == **canvas2,
+                _ => hovered.get(),
+            };
+            if !ours {
+                return;
+            }
+            // The arrow keys move the edit cursor instead of scrolling the page
+            if event.key().starts_with("Arrow") {
+                event.prevent_default();
+            }
+            // Read from the physical key, Shift turns the digits into other characters
+            let slot = event
+                .code()
+                .strip_prefix("Digit")
+                .and_then(|digit| digit.parse::<u32>().ok())
+                .filter(|digit| (1..=9).contains(digit));
+            if let (Some(slot), false, false) =
+                (slot, event.ctrl_key() || event.meta_key(), event.alt_key())
             {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("render pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
-                            store: true,
-                        },
-                    })],
-                    depth_stencil_attachment: None,
+                let _ = sender2.send(if event.shift_key() {
+                    CanvasEvent::SaveSlot(slot)
+                } else {
+                    CanvasEvent::RestoreSlot(slot)
                 });
-
-                render_pass.set_pipeline(&self.render_pipeline);
-                render_pass.set_bind_group(0, &self.texture_target_bind_group, &[]);
-                render_pass.set_bind_group(1, &self.uniforms_bind_group, &[]);
-                render_pass.draw(0..3, 0..1);
+                return;
             }
-        }
+            let (key, ctrl) = (event.key(), event.ctrl_key() || event.meta_key());
+            if !BINDINGS.iter().any(|binding| binding.matches(&key, ctrl)) {
+                return;
+            }
+            let _ = sender2.send(CanvasEvent::KeyDown { key, ctrl });
+        },
+    ));
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+    listeners
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Size of the boards compared, small enough to read back every generation
+    const TEST_BOARD_SIZE: u32 = 48;
+    const TEST_GENERATIONS: u32 = 16;
+
+    async fn test_state() -> State {
+        let doc = web_sys::window().unwrap().document().unwrap();
+        let canvas = doc
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let size = TEST_BOARD_SIZE.to_string();
+        canvas.set_attribute("data-board-width", &size).unwrap();
+        canvas.set_attribute("data-board-height", &size).unwrap();
+        canvas.set_width(TEST_BOARD_SIZE);
+        canvas.set_height(TEST_BOARD_SIZE);
+        doc.body().unwrap().append_child(&canvas).unwrap();
+        State::new(&canvas, Settings::from_canvas(&canvas))
+            .await
+            .unwrap()
     }
-}
 
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
-pub async fn run() {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-    console_log::init_with_level(log::Level::Warn).expect("Couldn't initialize logger");
+    /// Stamps a glider while paused and records a frame's brush pass with the brush held down
+    /// elsewhere. The pass copies the target texture over the source texture, so it must not
+    /// bring back what was there before the stamp.
+    #[wasm_bindgen_test]
+    async fn stamp_survives_the_brush_pass() {
+        let state = test_state().await;
+        state.set_paused(true);
+        let glider = Pattern::parse_rle("bo$2bo$3o!").unwrap();
+        state.stamp(&glider, 8, 8);
 
-    let window = web_sys::window().unwrap();
-    let doc = window.document().unwrap();
-    let canvas = doc.get_element_by_id("canvas").unwrap();
+        *state.mousedown.write().unwrap() = true;
+        *state.last_mousepos.write().unwrap() = Some((100, 100));
+        state.update();
+        let mut encoder = state
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        state.encode_draw(&mut encoder);
+        state.queue.submit(std::iter::once(encoder.finish()));
 
-    let canvas: &'static _ = Box::leak(Box::new(
-        canvas.dyn_into::<web_sys::HtmlCanvasElement>().unwrap(),
-    ));
+        let texels = state.decode_texels(&state.read_region(8, 8, 3, 3).await.unwrap());
+        for y in 0..3 {
+            for x in 0..3 {
+                let [r, g, b, _] = texels[(y * 3 + x) as usize];
+                let alive = r * r + g * g + b * b > 0.5;
+                assert_eq!(alive, glider.get(x, y), "cell ({}, {})", x, y);
+            }
+        }
+    }
 
-    canvas.set_width(1024);
-    canvas.set_height(1024);
+    /// A click on the top-left corner of the image lands on cell (0, 0), which `flip_y` moves
+    /// to the bottom-left canvas pixel.
+    #[wasm_bindgen_test]
+    async fn top_left_click_maps_to_the_first_cell() {
+        let state = test_state().await;
+        assert_eq!(state.screen_to_cell((0, 0)), (0, 0));
 
-    let state = Arc::new(State::new(&canvas).await);
+        *state.flip_y.write().unwrap() = true;
+        assert_eq!(
+            state.screen_to_cell((0, state.config.read().unwrap().height - 1)),
+            (0, 0)
+        );
+    }
 
-    let mut receiver = setup_listeners(&canvas);
+    /// Frames without any input in between still move the `time` uniform forward.
+    #[wasm_bindgen_test]
+    async fn time_advances_without_input() {
+        let state = test_state().await;
+        state.render(1000.0).unwrap();
+        let time = state.uniforms.read().unwrap().time;
+        state.render(1500.0).unwrap();
+        assert!(state.uniforms.read().unwrap().time > time);
+    }
 
-    {
-        let state2 = Arc::clone(&state);
-        let window2 = window.clone();
+    /// With the view zoomed in, pressing the mouse gives the stroke a seed on the cell under the
+    /// cursor, the same cell the brush is drawn at, not the canvas pixel.
+    #[wasm_bindgen_test]
+    async fn seed_is_the_drawn_cell() {
+        let state = Arc::new(test_state().await);
+        state.zoom_at((0, 0), 4.0);
+        state.input(&CanvasEvent::MouseMove(20, 36)).await;
+        state
+            .input(&CanvasEvent::MouseDown {
+                shift: false,
+                alt: false,
+            })
+            .await;
+        state.update();
 
-        let f = Rc::new(RefCell::<Option<Closure<dyn FnMut()>>>::new(None));
-        let g = f.clone();
-        *g.borrow_mut() = Some(Closure::new(move || {
-            state2.render().unwrap();
-            window2.request_animation_frame(f.borrow().as_ref().unwrap().as_ref().unchecked_ref());
-        }));
+        let (x, y) = state.screen_to_cell((20, 36));
+        let uniforms = state.uniforms.read().unwrap();
+        assert_eq!(uniforms.seed, [x as f32, y as f32]);
+        assert_eq!(uniforms.seed, uniforms.mouse_pos);
+    }
+
+    /// Draws the fullscreen triangle over the board with `fs_coordinates` and checks that the
+    /// corners and the center of the board saw the fragment positions they should, (x + 0.5,
+    /// y + 0.5) for texel (x, y). Anything else means the vertex shader no longer covers the
+    /// whole board or flips or offsets it.
+    #[wasm_bindgen_test]
+    async fn fullscreen_triangle_covers_the_board() {
+        let state = test_state().await;
+        let source = shader::Source::assemble(None).unwrap();
+        let shader = state
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Shader"),
+                source: wgpu::ShaderSource::Wgsl(source.text.as_str().into()),
+            });
+        let layout = state
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Coordinates Pipeline Layout"),
+                bind_group_layouts: &[],
+                push_constant_ranges: &[],
+            });
+        let pipeline = state
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Coordinates Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_compute",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_coordinates",
+                    targets: &[Some(state.cell_format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            });
+
+        let mut encoder = state
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("coordinates pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &state.layers[0].texture_target_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.draw(0..3, 0..1);
+        }
+        state.queue.submit(std::iter::once(encoder.finish()));
 
-        window.request_animation_frame(g.borrow().as_ref().unwrap().as_ref().unchecked_ref());
+        let max = TEST_BOARD_SIZE - 1;
+        let texels = [(0, 0), (max, 0), (0, max), (max, max), (max / 2, max / 2)];
+        for (x, y) in texels {
+            let [fx, fy, ..] =
+                state.decode_texels(&state.read_region(x, y, 1, 1).await.unwrap())[0];
+            // As precise as the cell format stores it
+            let [ex, ey, ..] = state.decode_texels(&state.encode_texels(&[[
+                x as f32 + 0.5,
+                y as f32 + 0.5,
+                0.0,
+                1.0,
+            ]]))[0];
+            assert_eq!([fx, fy], [ex, ey], "texel ({}, {})", x, y);
+        }
     }
 
-    loop {
-        tokio::select! {
-            Some(event) = receiver.recv() => {
-                state.input(&event);
-                state.update();
+    fn random_board(seed: u64, density: f32) -> sim::Board {
+        let mut rng = Rng(seed);
+        let mut board = sim::Board::new(TEST_BOARD_SIZE, TEST_BOARD_SIZE);
+        for y in 0..TEST_BOARD_SIZE {
+            for x in 0..TEST_BOARD_SIZE {
+                board.set(x, y, rng.next_f32() < density);
             }
         }
+        board
     }
-}
 
-fn setup_listeners(
-    canvas: &'static web_sys::HtmlCanvasElement,
-) -> tokio::sync::mpsc::UnboundedReceiver<CanvasEvent> {
-    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    /// Steps seeded random boards on the GPU and on the CPU reference in `sim` and compares
+    /// every generation, under a few rules, all boundary modes and both neighborhoods.
+    #[wasm_bindgen_test]
+    async fn gpu_step_matches_sim() {
+        let state = test_state().await;
+        let rules = ["B3/S23", "B36/S23", "B2/S", "B3678/S34678"];
+        let boundaries = [
+            (BOUNDARY_TORUS, sim::Boundary::Torus),
+            (BOUNDARY_DEAD, sim::Boundary::Dead),
+            (BOUNDARY_REFLECT, sim::Boundary::Reflect),
+        ];
+        let neighborhoods = [
+            (NEIGHBORHOOD_MOORE, sim::Neighborhood::Moore),
+            (NEIGHBORHOOD_VON_NEUMANN, sim::Neighborhood::VonNeumann),
+        ];
+        let mut seed = 0;
+        for rule_text in rules {
+            let rule = rule_text.parse::<Rule>().unwrap();
+            state.layers[0].set_rule(&state.queue, rule);
+            for (boundary_mode, boundary) in boundaries {
+                for (neighborhood_mode, neighborhood) in neighborhoods {
+                    seed += 1;
+                    {
+                        let mut uniforms = state.uniforms.write().unwrap();
+                        uniforms.boundary_mode = boundary_mode;
+                        uniforms.neighborhood = neighborhood_mode;
+                    }
+                    state.update();
+                    let cpu_rule = sim::Rule {
+                        birth: rule.birth,
+                        survive: rule.survive,
+                        boundary,
+                        neighborhood,
+                        kill_margin: 0,
+                    };
 
-    let sender2 = sender.clone();
-    {
-        let closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
-            let rect = canvas.get_bounding_client_rect();
-            let width = canvas.width() as f32;
-            let height = canvas.height() as f32;
-            let x = event.offset_x() as f32 * (width / rect.width() as f32);
-            let y = event.offset_y() as f32 * (height / rect.height() as f32);
-            sender2.send(CanvasEvent::MouseMove(x as u32, y as u32));
-        }) as Box<dyn FnMut(_)>);
+                    let mut expected = random_board(seed, 0.35);
+                    let start = Pattern {
+                        width: TEST_BOARD_SIZE,
+                        height: TEST_BOARD_SIZE,
+                        cells: expected.cells.iter().map(|&cell| cell != 0).collect(),
+                    };
+                    state.stamp(&start, 0, 0);
+                    for generation in 1..=TEST_GENERATIONS {
+                        state.step();
+                        expected = expected.step(cpu_rule);
+                        let cells: Vec<bool> = expected.cells.iter().map(|&c| c != 0).collect();
+                        assert!(
+                            state.board().await.unwrap().cells == cells,
+                            "{} {:?} {:?}, seed {}: generation {} differs",
+                            rule_text,
+                            boundary,
+                            neighborhood,
+                            seed,
+                            generation
+                        );
+                    }
+                }
+            }
+        }
+    }
 
-        canvas
-            .add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref())
-            .unwrap();
-        closure.forget();
+    /// Before any generation ran, every texel of a fresh state is a dead cell rather than
+    /// whatever the texture memory held.
+    #[wasm_bindgen_test]
+    async fn fresh_board_is_dead() {
+        let state = test_state().await;
+        let (width, height) = (state.texture_size.width, state.texture_size.height);
+        let texels = state.decode_texels(&state.read_region(0, 0, width, height).await.unwrap());
+        assert!(texels.iter().all(|&texel| texel == [0.0, 0.0, 0.0, 1.0]));
     }
 
-    let sender2 = sender.clone();
-    {
-        let closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
-            sender2.send(CanvasEvent::MouseDown);
-        }) as Box<dyn FnMut(_)>);
+    /// The stroke seed is only active while the button that started the stroke is held down.
+    #[wasm_bindgen_test]
+    async fn seed_is_inactive_after_release() {
+        let state = Arc::new(test_state().await);
+        state.input(&CanvasEvent::MouseMove(10, 10)).await;
+        state
+            .input(&CanvasEvent::MouseDown {
+                shift: false,
+                alt: false,
+            })
+            .await;
+        state.update();
+        assert!(state.uniforms.read().unwrap().seed[0] >= 0.0);
 
-        canvas
-            .add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref())
-            .unwrap();
-        closure.forget();
+        state.input(&CanvasEvent::MouseUp).await;
+        state.update();
+        assert_eq!(state.uniforms.read().unwrap().seed, Uniforms::new().seed);
     }
 
-    let sender2 = sender.clone();
-    {
-        let closure = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
-            sender2.send(CanvasEvent::MouseUp);
-        }) as Box<dyn FnMut(_)>);
+    #[wasm_bindgen_test]
+    fn migrate_settings_fills_missing_keys_of_version_1() {
+        let defaults = "v=2&rule=B3/S23&brick=0&zoom=1";
+        assert_eq!(
+            migrate_settings("zoom=4&rule=B36/S23", defaults),
+            "rule=B36/S23&brick=0&zoom=4"
+        );
+        assert_eq!(
+            migrate_settings("rule=B36/S23&unknown=1", defaults),
+            "rule=B36/S23&brick=0&zoom=1&unknown=1"
+        );
+        assert_eq!(
+            migrate_settings("v=2&zoom=4", defaults),
+            "v=2&zoom=4",
+            "current settings must stay untouched"
+        );
+    }
 
-        canvas
-            .add_event_listener_with_callback("mouseup", closure.as_ref().unchecked_ref())
-            .unwrap();
-        closure.forget();
+    #[wasm_bindgen_test]
+    async fn settings_round_trip() {
+        let state = test_state().await;
+        let current = "rule=B36/S23&brick=1&zoom=2&neighborhood=1";
+        state.apply_settings(current);
+        let changed = state.serialize_settings();
+        assert_ne!(changed, state.default_settings);
+
+        state.apply_settings(&changed);
+        assert_eq!(state.serialize_settings(), changed);
+
+        // Version 1 without `brick` and `neighborhood`, which go back to their defaults
+        let version_1: Vec<_> = changed
+            .split('&')
+            .filter(|pair| {
+                !["v", "brick", "neighborhood"].contains(&pair.split('=').next().unwrap())
+            })
+            .collect();
+        state.apply_settings(&version_1.join("&"));
+        let migrated = state.serialize_settings();
+        for pair in migrated.split('&') {
+            let key = pair.split('=').next().unwrap();
+            let expected = if ["brick", "neighborhood"].contains(&key) {
+                &state.default_settings
+            } else {
+                &changed
+            };
+            assert!(
+                expected.split('&').any(|expected| expected == pair),
+                "{} was not migrated from {}",
+                pair,
+                version_1.join("&")
+            );
+        }
     }
 
-    receiver
+    #[wasm_bindgen_test]
+    fn slots_of_every_version_parse() {
+        let rle = "x = 3, y = 1\n3o!";
+        let version_1 = parse_slot(&format!("12\n{}", rle)).unwrap();
+        let version_2 = parse_slot(&format!("{}\n12\n{}", SLOT_VERSION_TAG, rle)).unwrap();
+        assert_eq!(version_1.0, 12);
+        assert_eq!(version_1.1.cells, vec![true; 3]);
+        assert_eq!(version_1, version_2);
+        assert!(parse_slot("v2\nnot a generation\n3o!").is_none());
+    }
 }